@@ -0,0 +1,398 @@
+//! A lobby for racing on a local network: `race_server [ADDRESS] [--pack PACK]`
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "../race_protocol.rs"]
+mod race_protocol;
+
+use race_protocol::{
+    encode, ClientMessage, LobbyPlayer, RaceResult, Racer, ServerMessage, DEFAULT_RACE_PORT,
+};
+
+const DEFAULT_PACK: &str = "english";
+/// The countdown every player sees between the start message and the first word
+const COUNTDOWN_SECONDS: u64 = 5;
+/// Matches the timed test of the app
+const RACE_SECONDS: u64 = 60;
+/// Racers that haven't finished this long after the race should have ended are left out
+const FINISH_GRACE_SECONDS: u64 = 10;
+/// A player whose connection takes longer than this to take a message is dropped,
+/// so one stalled player can't hold up the lobby
+const WRITE_TIMEOUT_SECONDS: u64 = 2;
+
+enum Event {
+    Connected(usize, TcpStream),
+    Message(usize, ClientMessage),
+    Disconnected(usize),
+}
+
+struct Player {
+    /// None until the player sent their name
+    name: Option<String>,
+    stream: TcpStream,
+    ready: bool,
+    /// Set while the player is in the running race
+    racer: Option<Racer>,
+}
+
+struct Lobby {
+    players: BTreeMap<usize, Player>,
+    pack: String,
+    race_ends: Option<Instant>,
+}
+
+fn main() {
+    let mut address = format!("0.0.0.0:{}", DEFAULT_RACE_PORT);
+    let mut pack = DEFAULT_PACK.to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pack" => match args.next() {
+                Some(pack_id) => pack = pack_id,
+                None => exit_with("--pack needs the id of a word pack"),
+            },
+            _ => address = arg,
+        }
+    }
+
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(error) => exit_with(&format!("Could not listen on {}: {}", address, error)),
+    };
+    // The bound address, so a port of 0 shows the one that was picked
+    let address = listener
+        .local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or(address);
+    println!(
+        "Race server listening on {} with the {} pack",
+        address, pack
+    );
+
+    let (events, receiver) = mpsc::channel();
+    thread::spawn(move || accept_players(listener, events));
+
+    Lobby {
+        players: BTreeMap::new(),
+        pack,
+        race_ends: None,
+    }
+    .run(receiver);
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn accept_players(listener: TcpListener, events: Sender<Event>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(_) => continue,
+        };
+
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_write_timeout(Some(Duration::from_secs(WRITE_TIMEOUT_SECONDS)));
+        if events.send(Event::Connected(id, stream)).is_err() {
+            return;
+        }
+
+        let events = events.clone();
+        thread::spawn(move || read_messages(id, reader, events));
+    }
+}
+
+// Lines that aren't messages are ignored, the connection only ends when the socket does
+fn read_messages(id: usize, stream: TcpStream, events: Sender<Event>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if let Ok(message) = serde_json::from_str(&line) {
+            if events.send(Event::Message(id, message)).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = events.send(Event::Disconnected(id));
+}
+
+impl Player {
+    /// Closing the connection on a failed write ends its reader, which then reports the
+    /// player as disconnected like any other player that left
+    fn send(&mut self, line: &str) {
+        if self.stream.write_all(line.as_bytes()).is_err() {
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Lobby {
+    fn run(mut self, receiver: Receiver<Event>) {
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(250)) {
+                Ok(event) => self.handle(event),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if matches!(self.race_ends, Some(race_ends) if Instant::now() >= race_ends) {
+                self.finish_race();
+            }
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, stream) => {
+                self.players.insert(
+                    id,
+                    Player {
+                        name: None,
+                        stream,
+                        ready: false,
+                        racer: None,
+                    },
+                );
+            }
+            Event::Message(id, ClientMessage::Join { name }) => {
+                let name = self.unique_name(name.trim());
+                println!("{} joined", name);
+
+                if let Some(player) = self.players.get_mut(&id) {
+                    player.name = Some(name);
+                }
+                self.send_lobby();
+            }
+            Event::Message(id, ClientMessage::Ready { ready }) => {
+                if let Some(player) = self.players.get_mut(&id) {
+                    player.ready = ready;
+                }
+                self.send_lobby();
+                self.start_if_everyone_is_ready();
+            }
+            Event::Message(
+                id,
+                ClientMessage::Progress {
+                    words,
+                    correct_words,
+                },
+            ) => {
+                if let Some(racer) = self.players.get_mut(&id).and_then(|p| p.racer.as_mut()) {
+                    racer.words = words;
+                    racer.correct_words = correct_words;
+                }
+                self.send_standings();
+            }
+            Event::Message(id, ClientMessage::Finish { wpm, accuracy }) => {
+                if let Some(racer) = self.players.get_mut(&id).and_then(|p| p.racer.as_mut()) {
+                    racer.result = Some(RaceResult { wpm, accuracy });
+                }
+                self.send_standings();
+
+                if self.racers().iter().all(|racer| racer.result.is_some()) {
+                    self.finish_race();
+                }
+            }
+            Event::Disconnected(id) => {
+                if let Some(Player {
+                    name: Some(name), ..
+                }) = self.players.remove(&id)
+                {
+                    println!("{} left", name);
+                }
+                self.send_lobby();
+
+                if self.race_ends.is_some()
+                    && self.racers().iter().all(|racer| racer.result.is_some())
+                {
+                    self.finish_race();
+                } else {
+                    self.start_if_everyone_is_ready();
+                }
+            }
+        }
+    }
+
+    fn unique_name(&self, name: &str) -> String {
+        let name = if name.is_empty() { "Player" } else { name };
+        let taken = |candidate: &str| {
+            self.players
+                .values()
+                .any(|player| player.name.as_deref() == Some(candidate))
+        };
+
+        let mut candidate = name.to_string();
+        let mut number = 2;
+        while taken(&candidate) {
+            candidate = format!("{} ({})", name, number);
+            number += 1;
+        }
+        candidate
+    }
+
+    fn joined(&mut self) -> impl Iterator<Item = &mut Player> {
+        self.players
+            .values_mut()
+            .filter(|player| player.name.is_some())
+    }
+
+    fn racers(&self) -> Vec<Racer> {
+        self.players
+            .values()
+            .filter_map(|player| player.racer.clone())
+            .collect()
+    }
+
+    fn start_if_everyone_is_ready(&mut self) {
+        if self.race_ends.is_some() {
+            return;
+        }
+
+        let joined: Vec<&Player> = self
+            .players
+            .values()
+            .filter(|player| player.name.is_some())
+            .collect();
+        if joined.is_empty() || !joined.iter().all(|player| player.ready) {
+            return;
+        }
+
+        let start = encode(&ServerMessage::Start {
+            seed: rand::random(),
+            pack: self.pack.clone(),
+            countdown_seconds: COUNTDOWN_SECONDS,
+        });
+
+        for player in self.joined() {
+            player.ready = false;
+            player.racer = Some(Racer {
+                name: player.name.clone().unwrap_or_default(),
+                words: 0,
+                correct_words: 0,
+                result: None,
+            });
+            player.send(&start);
+        }
+
+        self.race_ends = Some(
+            Instant::now()
+                + Duration::from_secs(COUNTDOWN_SECONDS + RACE_SECONDS + FINISH_GRACE_SECONDS),
+        );
+        println!("Race started");
+        self.send_standings();
+    }
+
+    fn finish_race(&mut self) {
+        let mut racers = self.racers();
+        rank(&mut racers);
+
+        for (place, racer) in racers.iter().enumerate() {
+            match &racer.result {
+                Some(result) => println!("{}. {} - {} WPM", place + 1, racer.name, result.wpm),
+                None => println!("{}. {} - did not finish", place + 1, racer.name),
+            }
+        }
+
+        let results = encode(&ServerMessage::Results { racers });
+        for player in self.players.values_mut() {
+            if player.racer.take().is_some() {
+                player.send(&results);
+            }
+        }
+
+        self.race_ends = None;
+        self.send_lobby();
+    }
+
+    fn send_lobby(&mut self) {
+        let players = self
+            .players
+            .values()
+            .filter_map(|player| {
+                player.name.as_ref().map(|name| LobbyPlayer {
+                    name: name.clone(),
+                    ready: player.ready,
+                })
+            })
+            .collect();
+
+        let lobby = encode(&ServerMessage::Lobby { players });
+        for player in self.joined() {
+            player.send(&lobby);
+        }
+    }
+
+    fn send_standings(&mut self) {
+        let mut racers = self.racers();
+        rank(&mut racers);
+
+        let standings = encode(&ServerMessage::Standings { racers });
+        for player in self.players.values_mut() {
+            if player.racer.is_some() {
+                player.send(&standings);
+            }
+        }
+    }
+}
+
+/// Finished racers by WPM, then everyone else by how far they got
+fn rank(racers: &mut [Racer]) {
+    racers.sort_by(|a, b| match (&a.result, &b.result) {
+        (Some(a_result), Some(b_result)) => b_result.wpm.cmp(&a_result.wpm),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.correct_words.cmp(&a.correct_words),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn racer(name: &str, correct_words: usize, wpm: Option<u16>) -> Racer {
+        Racer {
+            name: name.to_string(),
+            words: correct_words,
+            correct_words,
+            result: wpm.map(|wpm| RaceResult { wpm, accuracy: 100 }),
+        }
+    }
+
+    fn names(racers: &[Racer]) -> Vec<&str> {
+        racers.iter().map(|racer| racer.name.as_str()).collect()
+    }
+
+    #[test]
+    fn finished_racers_come_first_by_wpm() {
+        let mut racers = vec![
+            racer("slow", 20, Some(40)),
+            racer("still typing", 50, None),
+            racer("fast", 30, Some(90)),
+        ];
+        rank(&mut racers);
+        assert_eq!(names(&racers), ["fast", "slow", "still typing"]);
+    }
+
+    #[test]
+    fn unfinished_racers_go_by_correct_words() {
+        let mut racers = vec![
+            racer("behind", 3, None),
+            racer("ahead", 12, None),
+            racer("middle", 7, None),
+        ];
+        rank(&mut racers);
+        assert_eq!(names(&racers), ["ahead", "middle", "behind"]);
+    }
+}
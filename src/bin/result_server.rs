@@ -0,0 +1,325 @@
+//! A reference endpoint for result submission: `result_server [ADDRESS] [--token TOKEN] [--file FILE]`
+//!
+//! `POST /results` stores a result as one line of the file, `GET /results` lists them
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+const DEFAULT_FILE: &str = "submitted_results.jsonl";
+const RESULTS_PATH: &str = "/results";
+/// Results are a few hundred bytes, anything this big isn't one
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const READ_TIMEOUT_SECONDS: u64 = 10;
+
+struct Settings {
+    token: Option<String>,
+    file: String,
+    /// The ids of every stored result, so a result sent again isn't stored twice
+    stored_ids: HashSet<String>,
+}
+
+/// The fields every submitted result has to have, everything else is stored as it came
+#[derive(Deserialize)]
+struct SubmittedResult {
+    /// Results from before the app sent one have none and are always stored
+    #[serde(default)]
+    id: String,
+    name: String,
+    mode: String,
+    seconds: u16,
+    wpm: u16,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+fn main() {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut settings = Settings {
+        token: None,
+        file: DEFAULT_FILE.to_string(),
+        stored_ids: HashSet::new(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--token" => match args.next() {
+                Some(token) => settings.token = Some(token),
+                None => exit_with("--token needs the token clients have to send"),
+            },
+            "--file" => match args.next() {
+                Some(file) => settings.file = file,
+                None => exit_with("--file needs the path results are stored in"),
+            },
+            _ => address = arg,
+        }
+    }
+
+    settings.stored_ids = stored_ids(&settings.file);
+
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(error) => exit_with(&format!("Could not listen on {}: {}", address, error)),
+    };
+    // The bound address, so a port of 0 shows the one that was picked
+    let address = listener
+        .local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or(address);
+    println!(
+        "Accepting results on http://{}{}, stored in {}",
+        address, RESULTS_PATH, settings.file
+    );
+
+    // The lock keeps lines from two requests from running into each other
+    let settings = Arc::new(Mutex::new(settings));
+    for stream in listener.incoming().flatten() {
+        let settings = settings.clone();
+        thread::spawn(move || handle_connection(stream, &settings));
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn handle_connection(mut stream: TcpStream, settings: &Mutex<Settings>) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECONDS)));
+
+    let (status, body) = match read_request(&stream) {
+        Ok(request) => respond(request, &mut settings.lock().unwrap()),
+        Err(error) => (400, error_body(&error)),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|error| error.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|error| error.to_string())?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| "Content-Length is not a number".to_string())?
+                }
+                "authorization" => authorization = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err("The body is too large".to_string());
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|error| error.to_string())?;
+
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body: String::from_utf8(body).map_err(|_| "The body is not UTF-8".to_string())?,
+    })
+}
+
+fn respond(request: Request, settings: &mut Settings) -> (u16, String) {
+    if request.path != RESULTS_PATH {
+        return (404, error_body("Results go to /results"));
+    }
+    if let Some(token) = &settings.token {
+        if request.authorization.as_deref() != Some(format!("Bearer {}", token).as_str()) {
+            return (401, error_body("A valid bearer token is needed"));
+        }
+    }
+
+    match request.method.as_str() {
+        "POST" => store_result(&request.body, settings),
+        "GET" => list_results(&settings.file),
+        _ => (405, error_body("Only GET and POST are supported")),
+    }
+}
+
+fn stored_ids(file: &str) -> HashSet<String> {
+    fs::read_to_string(file)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| serde_json::from_str::<SubmittedResult>(line).ok())
+                .map(|result| result.id)
+                .filter(|id| !id.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn store_result(body: &str, settings: &mut Settings) -> (u16, String) {
+    let result: SubmittedResult = match serde_json::from_str(body) {
+        Ok(result) => result,
+        Err(error) => return (400, error_body(&format!("Not a result: {}", error))),
+    };
+
+    // Sent again because the answer to the first attempt never arrived; it was stored already,
+    // which is all the app needs to hear
+    if settings.stored_ids.contains(&result.id) {
+        return (200, "{\"status\":\"duplicate\"}".to_string());
+    }
+
+    // Stored on one line, whatever formatting it was sent with
+    let line = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value) => value.to_string(),
+        Err(error) => return (400, error_body(&error.to_string())),
+    };
+
+    let stored = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&settings.file)
+        .and_then(|mut file| writeln!(file, "{}", line));
+
+    match stored {
+        Ok(()) => {
+            if !result.id.is_empty() {
+                settings.stored_ids.insert(result.id);
+            }
+            println!(
+                "{}: {} WPM in {} ({} seconds)",
+                result.name, result.wpm, result.mode, result.seconds
+            );
+            (201, "{\"status\":\"stored\"}".to_string())
+        }
+        Err(error) => (500, error_body(&error.to_string())),
+    }
+}
+
+fn list_results(file: &str) -> (u16, String) {
+    let results: Vec<serde_json::Value> = fs::read_to_string(file)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (200, serde_json::Value::from(results).to_string())
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings storing to a results file in a temp directory, removed when dropped
+    struct TestSettings {
+        settings: Settings,
+        _directory: tempfile::TempDir,
+    }
+
+    impl TestSettings {
+        fn new() -> Self {
+            let directory = tempfile::tempdir().unwrap();
+            let file = directory.path().join("results.jsonl");
+            Self {
+                settings: Settings {
+                    token: None,
+                    file: file.to_string_lossy().to_string(),
+                    stored_ids: HashSet::new(),
+                },
+                _directory: directory,
+            }
+        }
+
+        fn stored_lines(&self) -> usize {
+            fs::read_to_string(&self.settings.file)
+                .map(|contents| contents.lines().count())
+                .unwrap_or(0)
+        }
+    }
+
+    fn body(id: &str) -> String {
+        format!(
+            r#"{{"id":"{}","name":"ada","mode":"Random Words","seconds":60,"wpm":80}}"#,
+            id
+        )
+    }
+
+    #[test]
+    fn a_result_sent_again_is_stored_once() {
+        let mut settings = TestSettings::new();
+
+        assert_eq!(store_result(&body("ada-1"), &mut settings.settings).0, 201);
+        assert_eq!(store_result(&body("ada-1"), &mut settings.settings).0, 200);
+        assert_eq!(store_result(&body("ada-2"), &mut settings.settings).0, 201);
+        assert_eq!(settings.stored_lines(), 2);
+
+        // A restarted server still knows what it stored
+        let stored = stored_ids(&settings.settings.file);
+        assert!(stored.contains("ada-1") && stored.contains("ada-2"));
+    }
+
+    #[test]
+    fn results_without_an_id_are_always_stored() {
+        let mut settings = TestSettings::new();
+
+        assert_eq!(store_result(&body(""), &mut settings.settings).0, 201);
+        assert_eq!(store_result(&body(""), &mut settings.settings).0, 201);
+        assert_eq!(settings.stored_lines(), 2);
+        assert!(stored_ids(&settings.settings.file).is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::text::{grapheme_count, graphemes};
+
+const BOOK_PROGRESS_FILE: &str = "book_progress.json";
+
+pub const WORDS_PER_PAGE: usize = 120; //Should be divisible by 3
+
+const GUTENBERG_START_MARKER: &str = "*** START OF";
+const GUTENBERG_END_MARKER: &str = "*** END OF";
+
+pub struct Chapter {
+    pub title: String,
+    pub words: Vec<String>,
+}
+
+impl Chapter {
+    pub fn page_count(&self) -> usize {
+        self.words.len().div_ceil(WORDS_PER_PAGE).max(1)
+    }
+}
+
+pub struct Book {
+    pub title: String,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("The book could not be read: {}", error))?;
+
+        let fallback_title = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        let book = parse_book(&text, fallback_title);
+
+        if book.chapters.is_empty() {
+            return Err("The book does not contain any words".to_string());
+        }
+        Ok(book)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ChapterStats {
+    pub sessions: u32,
+    pub seconds: u32,
+    pub words_typed: u32,
+    pub correct_words: u32,
+    pub characters: u32,
+    pub correct_characters: u32,
+}
+
+impl ChapterStats {
+    pub fn wpm(&self) -> u32 {
+        if self.seconds == 0 {
+            return 0;
+        }
+        self.correct_words * 60 / self.seconds
+    }
+
+    pub fn accuracy(&self) -> u32 {
+        if self.characters == 0 {
+            return 0;
+        }
+        self.correct_characters * 100 / self.characters
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct BookProgress {
+    pub chapter: usize,
+    pub word_offset: usize,
+    pub finished: bool,
+    pub chapter_stats: Vec<ChapterStats>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct BookProgressFile {
+    last_book: Option<String>,
+    books: HashMap<String, BookProgress>,
+}
+
+pub struct BookState {
+    pub path_input: String,
+    pub load_error: Option<String>,
+    book: Option<Book>,
+    path: String,
+    progress: BookProgress,
+    saved: BookProgressFile,
+}
+
+impl BookState {
+    pub fn new() -> Self {
+        let saved = load_progress_file();
+        let mut state = Self {
+            path_input: String::new(),
+            load_error: None,
+            book: None,
+            path: String::new(),
+            progress: BookProgress::default(),
+            saved,
+        };
+
+        // Continue with the book from the last session, if there was one
+        if let Some(last_book) = state.saved.last_book.clone() {
+            state.path_input = last_book;
+            state.load_from_input();
+        }
+        state
+    }
+
+    pub fn book(&self) -> Option<&Book> {
+        self.book.as_ref()
+    }
+
+    pub fn progress(&self) -> &BookProgress {
+        &self.progress
+    }
+
+    pub fn load_from_input(&mut self) {
+        let path = self.path_input.trim().to_string();
+
+        match Book::load(&path) {
+            Ok(book) => {
+                let mut progress = self.saved.books.get(&path).cloned().unwrap_or_default();
+                progress
+                    .chapter_stats
+                    .resize(book.chapters.len(), ChapterStats::default());
+
+                // The file may have changed since the progress was saved
+                if progress.chapter >= book.chapters.len() {
+                    progress = BookProgress {
+                        chapter_stats: progress.chapter_stats,
+                        ..Default::default()
+                    };
+                } else if progress.word_offset >= book.chapters[progress.chapter].words.len() {
+                    progress.word_offset = 0;
+                }
+
+                self.book = Some(book);
+                self.path = path;
+                self.progress = progress;
+                self.load_error = None;
+                self.save();
+            }
+            Err(error) => {
+                self.load_error = Some(error);
+            }
+        }
+    }
+
+    /// The words left on the current page, which is what a single test covers
+    pub fn current_page_words(&self) -> Vec<String> {
+        if let Some(book) = &self.book {
+            let words = &book.chapters[self.progress.chapter].words;
+            let page_end = (self.current_page() + 1) * WORDS_PER_PAGE;
+
+            words[self.progress.word_offset..page_end.min(words.len())].to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn current_page(&self) -> usize {
+        self.progress.word_offset / WORDS_PER_PAGE
+    }
+
+    pub fn position_text(&self) -> String {
+        if let Some(book) = &self.book {
+            let chapter = &book.chapters[self.progress.chapter];
+
+            format!(
+                "{} - Page {}/{}",
+                chapter.title,
+                self.current_page() + 1,
+                chapter.page_count()
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    /// Moves the position forward by the words typed and adds the session to the chapter stats
+    pub fn record_session(
+        &mut self,
+        word_list: &[String],
+        player_word_list: &[String],
+        seconds: u32,
+    ) {
+        let book = match &self.book {
+            Some(book) => book,
+            None => return,
+        };
+
+        let stats = &mut self.progress.chapter_stats[self.progress.chapter];
+        stats.sessions += 1;
+        stats.seconds += seconds;
+
+        for (word, typed) in word_list.iter().zip(player_word_list.iter()) {
+            stats.words_typed += 1;
+            stats.characters += grapheme_count(typed) as u32;
+            stats.correct_characters += graphemes(word)
+                .into_iter()
+                .zip(graphemes(typed))
+                .filter(|(expected, actual)| expected == actual)
+                .count() as u32;
+
+            if word == typed {
+                stats.correct_words += 1;
+            }
+        }
+
+        self.progress.word_offset += player_word_list.len();
+
+        if self.progress.word_offset >= book.chapters[self.progress.chapter].words.len() {
+            if self.progress.chapter + 1 < book.chapters.len() {
+                self.progress.chapter += 1;
+            } else {
+                // Start over once the whole book has been typed
+                self.progress.chapter = 0;
+                self.progress.finished = true;
+            }
+            self.progress.word_offset = 0;
+        }
+
+        self.save();
+    }
+
+    fn save(&mut self) {
+        self.saved.last_book = Some(self.path.clone());
+        self.saved
+            .books
+            .insert(self.path.clone(), self.progress.clone());
+
+        if let Ok(json) = serde_json::to_string_pretty(&self.saved) {
+            let _ = fs::write(BOOK_PROGRESS_FILE, json);
+        }
+    }
+}
+
+fn load_progress_file() -> BookProgressFile {
+    fs::read_to_string(BOOK_PROGRESS_FILE)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn parse_book(text: &str, fallback_title: String) -> Book {
+    let mut title = fallback_title;
+    let mut chapters = Vec::new();
+    let mut current = Chapter {
+        title: "Opening".to_string(),
+        words: Vec::new(),
+    };
+
+    let mut lines: Vec<&str> = text.lines().collect();
+
+    // Project Gutenberg files wrap the book in a license header and footer
+    if let Some(start) = lines
+        .iter()
+        .position(|line| line.starts_with(GUTENBERG_START_MARKER))
+    {
+        for line in &lines[..start] {
+            if let Some(header_title) = line.strip_prefix("Title:") {
+                title = header_title.trim().to_string();
+            }
+        }
+        lines.drain(..=start);
+    }
+    if let Some(end) = lines
+        .iter()
+        .position(|line| line.starts_with(GUTENBERG_END_MARKER))
+    {
+        lines.truncate(end);
+    }
+
+    for line in lines {
+        let trimmed = line.trim();
+
+        if is_chapter_heading(trimmed) {
+            let finished = std::mem::replace(
+                &mut current,
+                Chapter {
+                    title: trimmed.to_string(),
+                    words: Vec::new(),
+                },
+            );
+            // A table of contents produces headings without any text
+            if !finished.words.is_empty() {
+                chapters.push(finished);
+            }
+            continue;
+        }
+
+        current.words.extend(
+            trimmed
+                .split_whitespace()
+                .map(normalize_word)
+                .filter(|word| !word.is_empty()),
+        );
+    }
+
+    if !current.words.is_empty() {
+        chapters.push(current);
+    }
+
+    Book { title, chapters }
+}
+
+fn is_chapter_heading(line: &str) -> bool {
+    let upper = line.to_uppercase();
+
+    ["CHAPTER ", "BOOK ", "PART "]
+        .iter()
+        .any(|prefix| upper.starts_with(prefix))
+        && line.split_whitespace().count() <= 8
+}
+
+// Typographic punctuation can't be typed on most keyboards so it gets plain equivalents
+fn normalize_word(word: &str) -> String {
+    let mut normalized = String::new();
+
+    for c in word.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' => normalized.push('\''),
+            '\u{201C}' | '\u{201D}' => normalized.push('"'),
+            '\u{2013}' | '\u{2014}' => normalized.push('-'),
+            '\u{2026}' => normalized.push_str("..."),
+            '_' => {}
+            c if c.is_control() => {}
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,308 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::fs;
+use std::path::Path;
+
+use crate::config::CodeSourceConfig;
+use crate::word_generator::WORD_COUNT_PER_GAME;
+
+const MAX_FILES_SCANNED: usize = 2000;
+const MAX_SNIPPET_LINES: usize = 40;
+const MIN_SNIPPET_WORDS: usize = 8;
+
+// Lines starting with one of these (after visibility and async keywords) open a function
+const FUNCTION_KEYWORDS: [&str; 6] = ["fn ", "def ", "function ", "func ", "fun ", "sub "];
+const LEADING_MODIFIERS: [&str; 7] = [
+    "pub(crate) ",
+    "pub ",
+    "async ",
+    "export ",
+    "static ",
+    "private ",
+    "public ",
+];
+
+pub struct Snippet {
+    pub words: Vec<String>,
+}
+
+pub struct CodeState {
+    pub directory_input: String,
+    pub extensions_input: String,
+    pub ignore_input: String,
+    pub scan_error: Option<String>,
+    snippets: Vec<Snippet>,
+}
+
+impl CodeState {
+    pub fn new(config: &CodeSourceConfig) -> Self {
+        let mut state = Self {
+            directory_input: config.directory.clone(),
+            extensions_input: config.extensions.join(", "),
+            ignore_input: config.ignore_patterns.join(", "),
+            scan_error: None,
+            snippets: Vec::new(),
+        };
+
+        if !config.directory.is_empty() {
+            state.scan(config);
+        }
+        state
+    }
+
+    pub fn snippets(&self) -> &Vec<Snippet> {
+        &self.snippets
+    }
+
+    /// The source settings as currently typed into the modes screen
+    pub fn config_from_input(&self) -> CodeSourceConfig {
+        CodeSourceConfig {
+            directory: self.directory_input.trim().to_string(),
+            extensions: split_list(&self.extensions_input)
+                .into_iter()
+                .map(|extension| extension.trim_start_matches('.').to_string())
+                .collect(),
+            ignore_patterns: split_list(&self.ignore_input),
+        }
+    }
+
+    pub fn scan(&mut self, config: &CodeSourceConfig) {
+        let root = Path::new(&config.directory);
+
+        if !root.is_dir() {
+            self.snippets.clear();
+            self.scan_error = Some(format!("{} is not a directory", config.directory));
+            return;
+        }
+
+        let mut files = Vec::new();
+        collect_files(root, root, config, &mut files);
+
+        self.snippets = files
+            .iter()
+            .flat_map(|file| extract_snippets(file))
+            .collect();
+
+        self.scan_error = if self.snippets.is_empty() {
+            Some("No functions were found in the matching files".to_string())
+        } else {
+            None
+        };
+    }
+
+    /// Random snippets joined together until there are enough words for a test
+    pub fn random_word_list(&self, rng: &mut StdRng) -> Vec<String> {
+        let mut words_for_game = Vec::new();
+
+        if self.snippets.is_empty() {
+            return words_for_game;
+        }
+
+        while words_for_game.len() < WORD_COUNT_PER_GAME {
+            let snippet = &self.snippets[rng.gen_range(0..self.snippets.len())];
+            words_for_game.extend(snippet.words.iter().cloned());
+        }
+        words_for_game
+    }
+}
+
+fn split_list(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn collect_files(
+    root: &Path,
+    directory: &Path,
+    config: &CodeSourceConfig,
+    files: &mut Vec<String>,
+) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        if files.len() >= MAX_FILES_SCANNED {
+            return;
+        }
+
+        let path = entry.path();
+        let relative_path = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if is_ignored(&relative_path, &config.ignore_patterns) {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(root, &path, config, files);
+        } else if has_extension(&path, &config.extensions) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    match path.extension() {
+        Some(extension) => extensions
+            .iter()
+            .any(|wanted| wanted.as_str() == extension.to_string_lossy()),
+        None => false,
+    }
+}
+
+/// Patterns match either a single path component or the whole relative path, with `*` as a wildcard
+fn is_ignored(relative_path: &str, ignore_patterns: &[String]) -> bool {
+    ignore_patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+
+        glob_match(pattern, relative_path)
+            || relative_path
+                .split('/')
+                .any(|component| glob_match(pattern, component))
+    })
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == text,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+
+            if !text.starts_with(prefix) {
+                return false;
+            }
+            let text = &text[prefix.len()..];
+
+            (0..=text.len())
+                .filter(|split| text.is_char_boundary(*split))
+                .any(|split| glob_match(rest, &text[split..]))
+        }
+    }
+}
+
+fn extract_snippets(file: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(_) => return snippets,
+    };
+
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut line_index = 0;
+
+    while line_index < lines.len() {
+        if !is_function_start(lines[line_index]) {
+            line_index += 1;
+            continue;
+        }
+
+        let block_end = find_block_end(&lines, line_index);
+        let words: Vec<String> = lines[line_index..block_end]
+            .iter()
+            .flat_map(|line| line.split_whitespace())
+            .map(|word| word.to_string())
+            .collect();
+
+        if words.len() >= MIN_SNIPPET_WORDS {
+            snippets.push(Snippet { words });
+        }
+
+        line_index = block_end;
+    }
+    snippets
+}
+
+fn is_function_start(line: &str) -> bool {
+    let mut trimmed = line.trim_start();
+
+    while let Some(modifier) = LEADING_MODIFIERS
+        .iter()
+        .find(|modifier| trimmed.starts_with(*modifier))
+    {
+        trimmed = &trimmed[modifier.len()..];
+    }
+
+    FUNCTION_KEYWORDS
+        .iter()
+        .any(|keyword| trimmed.starts_with(keyword))
+}
+
+/// Brace languages end when the braces balance out, others when the indentation returns
+fn find_block_end(lines: &[&str], start: usize) -> usize {
+    let start_indent = indentation(lines[start]);
+    let max_end = (start + MAX_SNIPPET_LINES).min(lines.len());
+    let mut depth: i32 = 0;
+    let mut seen_brace = false;
+
+    for (index, line) in lines.iter().enumerate().take(max_end).skip(start) {
+        for c in line.chars() {
+            if c == '{' {
+                depth += 1;
+                seen_brace = true;
+            } else if c == '}' {
+                depth -= 1;
+            }
+        }
+
+        if seen_brace && depth <= 0 {
+            return index + 1;
+        }
+
+        if !seen_brace
+            && index > start
+            && !line.trim().is_empty()
+            && indentation(line) <= start_indent
+        {
+            return index;
+        }
+    }
+    max_end
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_without_a_star_matches_exactly() {
+        assert!(glob_match("target", "target"));
+        assert!(!glob_match("target", "targets"));
+        assert!(!glob_match("target", "my_target"));
+    }
+
+    #[test]
+    fn glob_star_matches_any_run_of_characters() {
+        assert!(glob_match("*.min.js", "app.min.js"));
+        assert!(glob_match("*.min.js", ".min.js"));
+        assert!(!glob_match("*.min.js", "app.js"));
+        assert!(glob_match("test_*", "test_parser"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn glob_handles_several_stars() {
+        assert!(glob_match("*gen*", "codegen_output"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("a*b*c", "aXXcYYb"));
+    }
+
+    #[test]
+    fn glob_only_splits_on_character_boundaries() {
+        assert!(glob_match("*é", "café"));
+        assert!(glob_match("ü*", "über"));
+        assert!(!glob_match("*ß", "strasse"));
+    }
+}
@@ -6,5 +6,16 @@ pub const BUTTON_MENU_BACKGROUND_COLOR: Color32 = Color32::from_rgb(118, 102, 85
 
 pub const BUTTON_BACKGROUND_COLOR: Color32 = Color32::from_rgb(31, 25, 19);
 pub const BUTTON_STROKE_COLOR: Color32 = Color32::from_rgb(255, 206, 153);
+pub const BUTTON_FOCUSED_STROKE_COLOR: Color32 = Color32::from_rgb(255, 255, 255);
 pub const BUTTON_HOVERED_BACKGROUND_COLOR: Color32 = Color32::from_rgb(47, 38, 29);
 pub const BUTTON_ACTIVE_BACKGROUND_COLOR: Color32 = Color32::from_rgb(79, 64, 48);
+
+pub const LIGHT_GENERAL_BACKGROUND_COLOR: Color32 = Color32::from_rgb(214, 200, 182);
+
+pub const LIGHT_BUTTON_MENU_BACKGROUND_COLOR: Color32 = Color32::from_rgb(176, 154, 128);
+
+pub const LIGHT_BUTTON_BACKGROUND_COLOR: Color32 = Color32::from_rgb(245, 238, 228);
+pub const LIGHT_BUTTON_HOVERED_BACKGROUND_COLOR: Color32 = Color32::from_rgb(232, 222, 208);
+pub const LIGHT_BUTTON_ACTIVE_BACKGROUND_COLOR: Color32 = Color32::from_rgb(220, 204, 184);
+
+pub const PACE_CARET_COLOR: Color32 = Color32::from_rgb(40, 90, 160);
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::game_mode::GameMode;
+use crate::hotkeys::KeyBindings;
+use crate::modifiers::Modifiers;
+use crate::pace::PaceTarget;
+use crate::run_mode::RunMode;
+use crate::theme::ThemeKind;
+use crate::transforms::{pipeline_pack_words, Transform};
+use crate::word_filters::WordFilters;
+use crate::word_packs::WordPack;
+
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CodeSourceConfig {
+    pub directory: String,
+    pub extensions: Vec<String>,
+    pub ignore_patterns: Vec<String>,
+}
+
+impl Default for CodeSourceConfig {
+    fn default() -> Self {
+        Self {
+            directory: String::new(),
+            extensions: vec!["rs".to_string()],
+            ignore_patterns: vec![
+                "target".to_string(),
+                ".git".to_string(),
+                "node_modules".to_string(),
+            ],
+        }
+    }
+}
+
+/// Where results are shared with the team, nothing is shared while the directory is empty
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct LeaderboardConfig {
+    pub directory: String,
+    /// The name shown next to your results, the login name when empty
+    pub name: String,
+}
+
+impl LeaderboardConfig {
+    pub fn player_name(&self) -> String {
+        let name = self.name.trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "Player".to_string())
+    }
+}
+
+/// Where finished results are posted, nothing is posted while the endpoint is empty
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct SubmissionConfig {
+    pub endpoint: String,
+    /// Sent as a bearer token when the server wants one
+    pub token: String,
+}
+
+/// Everything the player chose that should survive a restart
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct UserConfig {
+    pub code_source: CodeSourceConfig,
+    pub modifiers: Modifiers,
+    pub transforms: Vec<Transform>,
+    pub word_pack: String,
+    pub word_filters: WordFilters,
+    /// Space doesn't move on while the current word is wrong
+    pub strict_mode: bool,
+    /// Letters that don't match the current word are never typed
+    pub stop_on_letter: bool,
+    pub key_bindings: KeyBindings,
+    pub theme: ThemeKind,
+    pub pace: PaceTarget,
+    pub leaderboard: LeaderboardConfig,
+    pub submission: SubmissionConfig,
+    /// Set for a proctored test, the settings can't be changed and are never saved
+    #[serde(skip)]
+    pub locked: bool,
+}
+
+impl UserConfig {
+    pub fn load() -> Self {
+        fs::read_to_string(CONFIG_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Tags for every setting that changes the generated words
+    pub fn word_tags(&self, word_pack: Option<&WordPack>) -> Vec<String> {
+        let mut tags = self.modifiers.tags();
+        // Like the filters, a transform that had nothing to work with isn't tagged
+        tags.extend(
+            self.transforms
+                .iter()
+                .filter(|transform| {
+                    word_pack.is_none_or(|word_pack| transform.applies_to(&word_pack.words))
+                })
+                .map(|transform| transform.tag().to_string()),
+        );
+        if let Some(word_pack) = word_pack {
+            let pack_words = pipeline_pack_words(&self.transforms, &word_pack.words);
+            if self.word_filters.apply_to(&pack_words) {
+                tags.extend(self.word_filters.tags());
+            }
+        }
+        tags
+    }
+
+    /// Tags for the settings that change how mistakes are handled, in every mode
+    pub fn typing_tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+
+        if self.strict_mode {
+            tags.push("strict".to_string());
+        }
+        if self.stop_on_letter {
+            tags.push("stop-on-letter".to_string());
+        }
+        tags
+    }
+
+    /// Everything a result is tagged with, so only runs under the same conditions are compared
+    pub fn result_tags(
+        &self,
+        game_mode: &GameMode,
+        run_mode: &RunMode,
+        word_pack: Option<&WordPack>,
+    ) -> Vec<String> {
+        let mut tags = Vec::new();
+
+        if *game_mode == GameMode::Words {
+            tags = self.word_tags(word_pack);
+        }
+        if let Some(tag) = run_mode.tag() {
+            tags.push(tag);
+        }
+        tags.extend(self.typing_tags());
+        tags
+    }
+
+    pub fn save(&self) {
+        if self.locked {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(CONFIG_FILE, json);
+        }
+    }
+}
@@ -3,8 +3,9 @@ use bevy_egui::egui::FontData;
 use bevy_egui::EguiContext;
 
 use crate::egui::{FontDefinitions, FontFamily, TextStyle};
+use crate::word_packs::WordPacks;
 
-pub fn setup_fonts(mut ctx: ResMut<EguiContext>) {
+pub fn setup_fonts(mut ctx: ResMut<EguiContext>, word_packs: Res<WordPacks>) {
     let mut fonts = FontDefinitions::default();
 
     fonts.font_data.insert(
@@ -18,6 +19,37 @@ pub fn setup_fonts(mut ctx: ResMut<EguiContext>) {
         .unwrap()
         .insert(0, "toxigenesis".to_owned());
 
+    // Covers Hebrew, Arabic and the other scripts the default fonts are missing
+    fonts.font_data.insert(
+        "dejavu".to_owned(),
+        FontData::from_static(include_bytes!("../assets/DejaVuSans.ttf")),
+    );
+
+    for family in [FontFamily::Monospace, FontFamily::Proportional] {
+        fonts
+            .fonts_for_family
+            .get_mut(&family)
+            .unwrap()
+            .push("dejavu".to_owned());
+    }
+
+    // Pack fonts only fill in the glyphs none of the fonts above have
+    for pack in &word_packs.packs {
+        if let Some(font) = &pack.font {
+            fonts
+                .font_data
+                .insert(pack.id.clone(), FontData::from_owned(font.clone()));
+
+            for family in [FontFamily::Monospace, FontFamily::Proportional] {
+                fonts
+                    .fonts_for_family
+                    .get_mut(&family)
+                    .unwrap()
+                    .push(pack.id.clone());
+            }
+        }
+    }
+
     fonts
         .family_and_size
         .insert(TextStyle::Small, (FontFamily::Monospace, 16.0));
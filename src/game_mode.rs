@@ -0,0 +1,32 @@
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameMode {
+    Words,
+    Book,
+    Code,
+    Numbers,
+    Symbols,
+    Review,
+}
+
+/// In the order the modes screen lists them
+pub const ALL_GAME_MODES: [GameMode; 6] = [
+    GameMode::Words,
+    GameMode::Numbers,
+    GameMode::Symbols,
+    GameMode::Book,
+    GameMode::Code,
+    GameMode::Review,
+];
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Words => "Random Words",
+            GameMode::Book => "Book",
+            GameMode::Code => "Code Snippets",
+            GameMode::Numbers => "Number Drill",
+            GameMode::Symbols => "Symbol Drill",
+            GameMode::Review => "Review",
+        }
+    }
+}
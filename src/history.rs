@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::key_stats::{merge_key_stats, KeyStats};
+
+const HISTORY_FILE: &str = "results.jsonl";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TestResult {
+    pub timestamp: u64,
+    pub mode: String,
+    pub seconds: u16,
+    pub cpm: u16,
+    pub correct_cpm: u16,
+    pub wpm: u16,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "KeyStats::is_empty")]
+    pub key_stats: KeyStats,
+    /// The run broke the rules of its run mode, like a wrong word in sudden death
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub failed: bool,
+    /// The run was paused at some point, so its speed isn't a fair comparison
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+}
+
+impl TestResult {
+    pub fn new(mode: &str, seconds: u16, cpm: u16, correct_cpm: u16, wpm: u16) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            mode: mode.to_string(),
+            seconds,
+            cpm,
+            correct_cpm,
+            wpm,
+            tags: Vec::new(),
+            key_stats: KeyStats::new(),
+            failed: false,
+            paused: false,
+        }
+    }
+
+    /// Results are only comparable when they were typed under the same conditions
+    pub fn is_comparable(&self, other: &TestResult) -> bool {
+        let mut tags = self.tags.clone();
+        let mut other_tags = other.tags.clone();
+        tags.sort();
+        other_tags.sort();
+
+        self.mode == other.mode && self.seconds == other.seconds && tags == other_tags
+    }
+}
+
+pub struct History {
+    results: Vec<TestResult>,
+}
+
+impl History {
+    /// Every line of the history file is one result; lines that can't be read are skipped
+    pub fn load() -> Self {
+        let results = fs::read_to_string(HISTORY_FILE)
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { results }
+    }
+
+    pub fn best_wpm(&self, result: &TestResult) -> Option<u16> {
+        self.results
+            .iter()
+            .filter(|previous| !previous.failed && !previous.paused)
+            .filter(|previous| previous.is_comparable(result))
+            .map(|previous| previous.wpm)
+            .max()
+    }
+
+    pub fn all_time_key_stats(&self, mode: &str) -> KeyStats {
+        let mut total = KeyStats::new();
+
+        for result in self.results.iter().filter(|result| result.mode == mode) {
+            merge_key_stats(&mut total, &result.key_stats);
+        }
+        total
+    }
+
+    pub fn record(&mut self, result: TestResult) {
+        if let Ok(json) = serde_json::to_string(&result) {
+            if let Ok(mut file) = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(HISTORY_FILE)
+            {
+                let _ = writeln!(file, "{}", json);
+            }
+        }
+        self.results.push(result);
+    }
+}
@@ -0,0 +1,414 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::UserConfig;
+use crate::AppState;
+
+/// Everything the keyboard can do besides typing
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    Restart,
+    Menu,
+    Settings,
+    Pause,
+    OpenStats,
+    SwitchMode,
+    ToggleTheme,
+    FocusUp,
+    FocusDown,
+    Activate,
+}
+
+pub const ALL_ACTIONS: [Action; 10] = [
+    Action::Restart,
+    Action::Menu,
+    Action::Settings,
+    Action::Pause,
+    Action::OpenStats,
+    Action::SwitchMode,
+    Action::ToggleTheme,
+    Action::FocusUp,
+    Action::FocusDown,
+    Action::Activate,
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Restart => "Restart",
+            Action::Menu => "Menu",
+            Action::Settings => "Settings",
+            Action::Pause => "Pause",
+            Action::OpenStats => "Last Results",
+            Action::SwitchMode => "Next Mode",
+            Action::ToggleTheme => "Toggle Theme",
+            Action::FocusUp => "Previous Button",
+            Action::FocusDown => "Next Button",
+            Action::Activate => "Press Button",
+        }
+    }
+
+    /// The arrows and Enter belong to the input field while a test is running
+    fn is_navigation(&self) -> bool {
+        matches!(self, Action::FocusUp | Action::FocusDown | Action::Activate)
+    }
+
+    fn default_binding(&self) -> KeyBinding {
+        match self {
+            Action::Restart => KeyBinding::new(Some(HeldKey::Tab), KeyCode::Return),
+            Action::Menu => KeyBinding::new(None, KeyCode::Escape),
+            Action::Settings => KeyBinding::new(Some(HeldKey::Ctrl), KeyCode::Comma),
+            Action::Pause => KeyBinding::new(Some(HeldKey::Ctrl), KeyCode::P),
+            Action::OpenStats => KeyBinding::new(Some(HeldKey::Ctrl), KeyCode::R),
+            Action::SwitchMode => KeyBinding::new(Some(HeldKey::Ctrl), KeyCode::M),
+            Action::ToggleTheme => KeyBinding::new(Some(HeldKey::Ctrl), KeyCode::T),
+            Action::FocusUp => KeyBinding::new(None, KeyCode::Up),
+            Action::FocusDown => KeyBinding::new(None, KeyCode::Down),
+            Action::Activate => KeyBinding::new(None, KeyCode::Return),
+        }
+    }
+}
+
+/// A key that has to be held down for a binding to trigger
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HeldKey {
+    Ctrl,
+    Shift,
+    Alt,
+    Tab,
+}
+
+const ALL_HELD_KEYS: [HeldKey; 4] = [HeldKey::Ctrl, HeldKey::Shift, HeldKey::Alt, HeldKey::Tab];
+
+impl HeldKey {
+    fn is_held(&self, keys: &Input<KeyCode>) -> bool {
+        match self {
+            HeldKey::Ctrl => keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl),
+            HeldKey::Shift => keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift),
+            HeldKey::Alt => keys.pressed(KeyCode::LAlt) || keys.pressed(KeyCode::RAlt),
+            HeldKey::Tab => keys.pressed(KeyCode::Tab),
+        }
+    }
+
+    fn is_key(&self, key: KeyCode) -> bool {
+        match self {
+            HeldKey::Ctrl => key == KeyCode::LControl || key == KeyCode::RControl,
+            HeldKey::Shift => key == KeyCode::LShift || key == KeyCode::RShift,
+            HeldKey::Alt => key == KeyCode::LAlt || key == KeyCode::RAlt,
+            HeldKey::Tab => key == KeyCode::Tab,
+        }
+    }
+}
+
+// Keys that can be bound, with the names they are saved under
+const BINDABLE_KEYS: [(KeyCode, &str); 58] = [
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::F1, "F1"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::F12, "F12"),
+    (KeyCode::Return, "Enter"),
+    (KeyCode::Escape, "Escape"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Comma, ","),
+    (KeyCode::Period, "."),
+    (KeyCode::Slash, "/"),
+    (KeyCode::Pause, "Pause"),
+];
+
+// Keys that put a character in the input field, alone or with Shift
+fn is_printable(key: KeyCode) -> bool {
+    matches!(key_name(key), Some(name) if name.chars().count() == 1)
+}
+
+fn key_name(key: KeyCode) -> Option<&'static str> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|(bindable, _)| *bindable == key)
+        .map(|(_, name)| *name)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|(_, bindable_name)| *bindable_name == name)
+        .map(|(key, _)| *key)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyBinding {
+    pub held: Option<HeldKey>,
+    pub key: KeyCode,
+}
+
+impl KeyBinding {
+    fn new(held: Option<HeldKey>, key: KeyCode) -> Self {
+        Self { held, key }
+    }
+
+    // A plain key doesn't trigger while a held key is down, so Enter and Tab+Enter stay apart
+    fn is_triggered(&self, keys: &Input<KeyCode>) -> bool {
+        keys.just_pressed(self.key)
+            && match self.held {
+                Some(held) => held.is_held(keys),
+                None => !ALL_HELD_KEYS.iter().any(|held| held.is_held(keys)),
+            }
+    }
+
+    /// Typing the letter or digit in a test would trigger a binding like this
+    fn types_text(&self) -> bool {
+        is_printable(self.key) && matches!(self.held, None | Some(HeldKey::Shift))
+    }
+
+    pub fn text(&self) -> String {
+        let key = key_name(self.key).unwrap_or("?");
+
+        match self.held {
+            Some(held) => format!("{:?}+{}", held, key),
+            None => key.to_string(),
+        }
+    }
+}
+
+/// How a binding is stored in the config file
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedBinding {
+    action: Action,
+    held: Option<HeldKey>,
+    key: String,
+}
+
+/// One binding per action, saved with the rest of the user config
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "Vec<SavedBinding>", into = "Vec<SavedBinding>")]
+pub struct KeyBindings {
+    pub bindings: Vec<(Action, KeyBinding)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: ALL_ACTIONS
+                .iter()
+                .map(|action| (*action, action.default_binding()))
+                .collect(),
+        }
+    }
+}
+
+// Actions missing from the file, or bound to a key this version doesn't know, keep their default
+impl From<Vec<SavedBinding>> for KeyBindings {
+    fn from(saved_bindings: Vec<SavedBinding>) -> Self {
+        let mut key_bindings = KeyBindings::default();
+
+        for saved in saved_bindings {
+            if let Some(key) = key_from_name(&saved.key) {
+                key_bindings.set(saved.action, KeyBinding::new(saved.held, key));
+            }
+        }
+        key_bindings
+    }
+}
+
+impl From<KeyBindings> for Vec<SavedBinding> {
+    fn from(key_bindings: KeyBindings) -> Self {
+        key_bindings
+            .bindings
+            .iter()
+            .map(|(action, binding)| SavedBinding {
+                action: *action,
+                held: binding.held,
+                key: key_name(binding.key).unwrap_or("?").to_string(),
+            })
+            .collect()
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: Action) -> KeyBinding {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _)| *bound_action == action)
+            .map(|(_, binding)| *binding)
+            .unwrap_or_else(|| action.default_binding())
+    }
+
+    fn set(&mut self, action: Action, binding: KeyBinding) {
+        for (bound_action, bound) in self.bindings.iter_mut() {
+            if *bound_action == action {
+                *bound = binding;
+            }
+        }
+    }
+
+    /// Binds the action unless another action already uses the same keys
+    pub fn rebind(&mut self, action: Action, binding: KeyBinding) -> Result<(), String> {
+        if binding.types_text() {
+            return Err(format!(
+                "{} is typed in tests, hold Ctrl, Alt or Tab with it",
+                binding.text()
+            ));
+        }
+        if let Some(other) = self.action_for(binding, action) {
+            return Err(format!(
+                "{} is already used by {}",
+                binding.text(),
+                other.name()
+            ));
+        }
+        self.set(action, binding);
+        Ok(())
+    }
+
+    /// Actions sharing a binding, which can only happen when the config was edited by hand
+    pub fn conflicts(&self) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter_map(|(action, binding)| {
+                self.action_for(*binding, *action)
+                    .filter(|other| {
+                        ALL_ACTIONS.iter().position(|a| a == other)
+                            > ALL_ACTIONS.iter().position(|a| a == action)
+                    })
+                    .map(|other| {
+                        format!(
+                            "{} is used by both {} and {}",
+                            binding.text(),
+                            action.name(),
+                            other.name()
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn action_for(&self, binding: KeyBinding, except: Action) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(action, bound)| *action != except && *bound == binding)
+            .map(|(action, _)| *action)
+    }
+}
+
+/// The action waiting for a new key on the settings screen
+#[derive(Default)]
+pub struct Rebinding {
+    pub action: Option<Action>,
+    pub error: Option<String>,
+}
+
+impl Rebinding {
+    /// Takes the first key pressed, together with whatever held key is down; Escape cancels
+    pub fn capture(&mut self, keys: &Input<KeyCode>, key_bindings: &mut KeyBindings) -> bool {
+        let action = match self.action {
+            Some(action) => action,
+            None => return false,
+        };
+
+        if keys.just_pressed(KeyCode::Escape) {
+            self.action = None;
+            return false;
+        }
+
+        let key = keys.get_just_pressed().copied().find(|key| {
+            key_name(*key).is_some() && !ALL_HELD_KEYS.iter().any(|held| held.is_key(*key))
+        });
+
+        let key = match key {
+            Some(key) => key,
+            None => return false,
+        };
+        let held = ALL_HELD_KEYS
+            .iter()
+            .copied()
+            .find(|held| held.is_held(keys));
+
+        self.action = None;
+        match key_bindings.rebind(action, KeyBinding::new(held, key)) {
+            Ok(()) => {
+                self.error = None;
+                true
+            }
+            Err(error) => {
+                self.error = Some(error);
+                false
+            }
+        }
+    }
+}
+
+/// The side panel button the arrow keys moved to, if any
+pub struct SidePanelFocus(pub Option<usize>);
+
+/// Turns key presses into actions; the systems owning the buttons carry them out
+pub fn map_hotkeys(
+    keys: Res<Input<KeyCode>>,
+    app_state: Res<State<AppState>>,
+    config: Res<UserConfig>,
+    rebinding: Res<Rebinding>,
+    mut actions: EventWriter<Action>,
+) {
+    // The keys pressed while rebinding are meant for the new binding
+    if rebinding.action.is_some() {
+        return;
+    }
+
+    let testing =
+        app_state.current() == &AppState::Playing || app_state.current() == &AppState::Paused;
+
+    for (action, binding) in &config.key_bindings.bindings {
+        // A letter bound in a hand edited config is still just a letter during a test
+        if testing && (action.is_navigation() || binding.types_text()) {
+            continue;
+        }
+        if binding.is_triggered(&keys) {
+            actions.send(*action);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+
+/// Where a background thread leaves what it found for the app to pick up on a later frame.
+/// Resources have to be Sync, which a Receiver on its own isn't
+pub struct Inbox<T> {
+    receiver: Mutex<Receiver<T>>,
+}
+
+impl<T> Inbox<T> {
+    /// The sender goes to the thread, the inbox stays with the resource
+    pub fn channel() -> (Sender<T>, Self) {
+        let (sender, receiver) = mpsc::channel();
+        (
+            sender,
+            Self {
+                receiver: Mutex::new(receiver),
+            },
+        )
+    }
+
+    /// The next thing sent, without waiting for it; Disconnected once the sender is gone
+    pub fn try_take(&self) -> Result<T, TryRecvError> {
+        self.receiver.lock().unwrap().try_recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_what_was_sent_then_reports_the_sender_gone() {
+        let (sender, inbox) = Inbox::channel();
+        assert_eq!(inbox.try_take(), Err(TryRecvError::Empty));
+
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        drop(sender);
+
+        assert_eq!(inbox.try_take(), Ok(1));
+        assert_eq!(inbox.try_take(), Ok(2));
+        assert_eq!(inbox.try_take(), Err(TryRecvError::Disconnected));
+    }
+}
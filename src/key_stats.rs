@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::text::graphemes;
+
+// Longer symbols come first so `&&` isn't counted as two `&`
+const SYMBOLS: [&str; 42] = [
+    "#[", "->", "=>", "::", "&&", "||", "!=", "==", "<=", ">=", "+=", "-=", "..", "//", "/*", "*/",
+    "(", ")", "[", "]", "{", "}", "<", ">", "&", "|", "*", "!", "?", ";", ":", ",", ".", "'", "\"",
+    "=", "+", "-", "%", "^", "@", "$",
+];
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct KeyCount {
+    pub attempts: u32,
+    pub correct: u32,
+}
+
+impl KeyCount {
+    pub fn accuracy(&self) -> u32 {
+        if self.attempts == 0 {
+            return 0;
+        }
+        self.correct * 100 / self.attempts
+    }
+}
+
+/// How often each key (or symbol) was attempted and typed correctly
+pub type KeyStats = BTreeMap<String, KeyCount>;
+
+/// Every digit in the words the player reached counts as an attempt for that digit
+pub fn get_digit_stats(word_list: &[String], player_word_list: &[String]) -> KeyStats {
+    let mut key_stats = KeyStats::new();
+
+    for (word, typed) in word_list.iter().zip(player_word_list.iter()) {
+        let mut typed_letters = graphemes(typed).into_iter();
+
+        for expected in graphemes(word) {
+            let actual = typed_letters.next();
+
+            if !expected.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+
+            let key_count = key_stats.entry(expected.to_string()).or_default();
+            key_count.attempts += 1;
+            if actual == Some(expected) {
+                key_count.correct += 1;
+            }
+        }
+    }
+    key_stats
+}
+
+/// Every symbol in the words the player reached counts as an attempt for that symbol
+pub fn get_symbol_stats(word_list: &[String], player_word_list: &[String]) -> KeyStats {
+    let mut key_stats = KeyStats::new();
+
+    for (word, typed) in word_list.iter().zip(player_word_list.iter()) {
+        let word_letters = graphemes(word);
+        let typed_letters = graphemes(typed);
+        let mut position = 0;
+
+        while position < word_letters.len() {
+            let rest = word_letters[position..].concat();
+            let symbol = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol));
+
+            match symbol {
+                Some(symbol) => {
+                    // Symbols are ASCII so every char is its own grapheme
+                    let end = position + symbol.len();
+                    let key_count = key_stats.entry(symbol.to_string()).or_default();
+
+                    key_count.attempts += 1;
+                    if typed_letters
+                        .get(position..end)
+                        .map(|letters| letters.concat())
+                        == Some(symbol.to_string())
+                    {
+                        key_count.correct += 1;
+                    }
+                    position = end;
+                }
+                None => position += 1,
+            }
+        }
+    }
+    key_stats
+}
+
+pub fn merge_key_stats(total: &mut KeyStats, key_stats: &KeyStats) {
+    for (key, count) in key_stats {
+        let total_count = total.entry(key.clone()).or_default();
+        total_count.attempts += count.attempts;
+        total_count.correct += count.correct;
+    }
+}
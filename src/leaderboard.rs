@@ -0,0 +1,696 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align, Color32, Label, Layout, RichText, ScrollArea};
+use bevy_egui::EguiContext;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{LeaderboardConfig, UserConfig};
+use crate::game_mode::{GameMode, ALL_GAME_MODES};
+use crate::history::TestResult;
+use crate::inbox::Inbox;
+use crate::widgets::StyledCentralPanel;
+use crate::AppState;
+
+const LEADERBOARD_FILE: &str = "leaderboard.jsonl";
+/// Created next to the file while a result is appended; creating a directory is atomic even on
+/// network mounts, where file locks often aren't supported
+const LOCK_DIRECTORY: &str = "leaderboard.lock";
+const LOCK_RETRY_MILLISECONDS: u64 = 50;
+const LOCK_TIMEOUT_SECONDS: u64 = 5;
+/// A lock this old was left behind by an app that was closed mid write
+const STALE_LOCK_SECONDS: u64 = 30;
+/// Holds the token of the app that created the lock, so it is only ever removed by its owner
+/// or, once stale, by the one app that moved it aside
+const LOCK_OWNER_FILE: &str = "owner";
+
+const RANKING_HEIGHT: f32 = 300.;
+
+/// One result on the shared leaderboard file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub timestamp: u64,
+    pub mode: String,
+    pub seconds: u16,
+    pub wpm: u16,
+    pub accuracy: u16,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The word pack of word and review tests, empty in the other modes
+    #[serde(default)]
+    pub pack: String,
+}
+
+impl LeaderboardEntry {
+    /// Tags in the order they are compared and shown in
+    fn sorted_tags(&self) -> Vec<String> {
+        let mut tags = self.tags.clone();
+        tags.sort();
+        tags
+    }
+}
+
+/// The best result of one player
+pub struct Standing {
+    pub name: String,
+    pub wpm: u16,
+    pub accuracy: u16,
+    pub tests: usize,
+}
+
+/// Appends the result to the shared file without holding up the frame, a slow or missing
+/// network mount only means the result isn't shared
+pub fn share_result(config: &LeaderboardConfig, result: &TestResult, accuracy: u16, pack: &str) {
+    if config.directory.trim().is_empty() {
+        return;
+    }
+
+    let directory = PathBuf::from(config.directory.trim());
+    let entry = LeaderboardEntry {
+        name: config.player_name(),
+        timestamp: result.timestamp,
+        mode: result.mode.clone(),
+        seconds: result.seconds,
+        wpm: result.wpm,
+        accuracy,
+        tags: result.tags.clone(),
+        pack: pack.to_string(),
+    };
+
+    thread::spawn(move || {
+        let _ = append_entry(&directory, &entry);
+    });
+}
+
+fn append_entry(directory: &Path, entry: &LeaderboardEntry) -> Result<(), String> {
+    let mut line = serde_json::to_string(entry).map_err(|error| error.to_string())?;
+    line.push('\n');
+
+    let _lock = LeaderboardLock::acquire(directory)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(directory.join(LEADERBOARD_FILE))
+        .map_err(|error| format!("The leaderboard could not be opened: {}", error))?;
+
+    // One write for the whole line, flushed before the lock goes so the next writer sees it
+    file.write_all(line.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|error| format!("The result could not be shared: {}", error))
+}
+
+struct LeaderboardLock {
+    path: PathBuf,
+    token: String,
+}
+
+impl LeaderboardLock {
+    fn acquire(directory: &Path) -> Result<Self, String> {
+        let path = directory.join(LOCK_DIRECTORY);
+        let token = format!("{:016x}", rand::random::<u64>());
+        let started = Instant::now();
+        let mut share_clock: Option<ShareClock> = None;
+
+        loop {
+            match fs::create_dir(&path) {
+                Ok(()) => {
+                    if let Err(error) = fs::write(path.join(LOCK_OWNER_FILE), &token) {
+                        let _ = fs::remove_dir(&path);
+                        return Err(format!("The leaderboard could not be locked: {}", error));
+                    }
+                    return Ok(Self { path, token });
+                }
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                    let owner = lock_owner(&path);
+                    let now = share_clock
+                        .get_or_insert_with(|| ShareClock::read(directory, &token))
+                        .now();
+
+                    if is_stale(&path, now) {
+                        take_over(directory, &path, owner.as_deref(), &token);
+                        continue;
+                    }
+                    if started.elapsed() > Duration::from_secs(LOCK_TIMEOUT_SECONDS) {
+                        return Err("Someone else is holding the leaderboard lock".to_string());
+                    }
+                    thread::sleep(Duration::from_millis(LOCK_RETRY_MILLISECONDS));
+                }
+                Err(error) => {
+                    return Err(format!("The leaderboard could not be locked: {}", error));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LeaderboardLock {
+    // A lock that was taken over while this app was stuck belongs to someone else now
+    fn drop(&mut self) {
+        if lock_owner(&self.path).as_deref() == Some(self.token.as_str()) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+fn lock_owner(lock_path: &Path) -> Option<String> {
+    fs::read_to_string(lock_path.join(LOCK_OWNER_FILE)).ok()
+}
+
+/// The time on the share, so a machine whose own clock is off doesn't take a lock over
+/// too early or too late; it is read once from a file written there and counted on locally
+struct ShareClock {
+    share_time: SystemTime,
+    read_at: Instant,
+}
+
+impl ShareClock {
+    fn read(directory: &Path, token: &str) -> Self {
+        let probe = directory.join(format!("{}.clock.{}", LOCK_DIRECTORY, token));
+        let share_time = fs::write(&probe, token)
+            .and_then(|_| fs::metadata(&probe))
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        let _ = fs::remove_file(&probe);
+
+        Self {
+            share_time,
+            read_at: Instant::now(),
+        }
+    }
+
+    fn now(&self) -> SystemTime {
+        self.share_time + self.read_at.elapsed()
+    }
+}
+
+fn is_stale(lock_path: &Path, now: SystemTime) -> bool {
+    let age = fs::metadata(lock_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok());
+
+    matches!(age, Some(age) if age > Duration::from_secs(STALE_LOCK_SECONDS))
+}
+
+/// Renaming is atomic, so when several apps find the same stale lock only one of them moves it
+/// aside. If what was moved isn't the lock that was found stale, another app took the lock in
+/// the meantime, and it is put back
+fn take_over(directory: &Path, lock_path: &Path, stale_owner: Option<&str>, token: &str) {
+    let aside = directory.join(format!("{}.stale.{}", LOCK_DIRECTORY, token));
+    if fs::rename(lock_path, &aside).is_err() {
+        return;
+    }
+
+    if lock_owner(&aside).as_deref() == stale_owner {
+        let _ = fs::remove_dir_all(&aside);
+    } else {
+        let _ = fs::rename(&aside, lock_path);
+    }
+}
+
+/// Lines that can't be read are skipped, like a line another app is still writing
+fn load_entries(directory: &str) -> Result<Vec<LeaderboardEntry>, String> {
+    let directory = Path::new(directory.trim());
+    if !directory.is_dir() {
+        return Err(format!("{} is not a directory", directory.display()));
+    }
+
+    match fs::read_to_string(directory.join(LEADERBOARD_FILE)) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        // Nobody shared a result yet
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(format!("The leaderboard could not be read: {}", error)),
+    }
+}
+
+/// Every player's best result typed under exactly these conditions, the fastest first
+fn rank(
+    entries: &[LeaderboardEntry],
+    mode: &str,
+    seconds: u16,
+    pack: &str,
+    tags: &[String],
+) -> Vec<Standing> {
+    let mut standings: BTreeMap<&str, Standing> = BTreeMap::new();
+
+    for entry in entries.iter().filter(|entry| {
+        entry.mode == mode
+            && entry.seconds == seconds
+            && entry.pack == pack
+            && entry.sorted_tags() == tags
+    }) {
+        let standing = standings
+            .entry(entry.name.as_str())
+            .or_insert_with(|| Standing {
+                name: entry.name.clone(),
+                wpm: 0,
+                accuracy: 0,
+                tests: 0,
+            });
+
+        standing.tests += 1;
+        if (entry.wpm, entry.accuracy) > (standing.wpm, standing.accuracy) {
+            standing.wpm = entry.wpm;
+            standing.accuracy = entry.accuracy;
+        }
+    }
+
+    let mut standings: Vec<Standing> = standings.into_values().collect();
+    standings.sort_by_key(|standing| Reverse((standing.wpm, standing.accuracy)));
+    standings
+}
+
+/// What reading the leaderboard file gave
+type LoadedEntries = Result<Vec<LeaderboardEntry>, String>;
+
+/// The shared results as last read, and what the leaderboard screen shows of them
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+    pub mode: GameMode,
+    /// The test duration, 0 for untimed runs
+    pub seconds: u16,
+    /// Only used by the modes that are typed on a pack
+    pub pack: String,
+    /// Runs are ranked against others with the same tags, untagged runs by default
+    pub tags: Vec<String>,
+    pub error: Option<String>,
+    /// The file is read again every time the screen is opened
+    loaded: bool,
+    /// The file is read on its own thread, a slow network mount would hold up the frame
+    loading: Option<Inbox<LoadedEntries>>,
+}
+
+impl Default for Leaderboard {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            mode: GameMode::Words,
+            seconds: 60,
+            pack: String::new(),
+            tags: Vec::new(),
+            error: None,
+            loaded: false,
+            loading: None,
+        }
+    }
+}
+
+impl Leaderboard {
+    fn load(&mut self, directory: &str) {
+        let (sender, loading) = Inbox::channel();
+        let directory = directory.to_string();
+        thread::spawn(move || {
+            let _ = sender.send(load_entries(&directory));
+        });
+
+        self.loading = Some(loading);
+        self.loaded = true;
+    }
+
+    /// Takes the entries once the file has been read, the ones shown until then stay
+    fn receive(&mut self) {
+        let loaded = match &self.loading {
+            Some(loading) => match loading.try_take() {
+                Ok(loaded) => loaded,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    Err("The leaderboard could not be read".to_string())
+                }
+            },
+            None => return,
+        };
+        self.loading = None;
+
+        match loaded {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(error) => {
+                self.entries.clear();
+                self.error = Some(error);
+            }
+        }
+    }
+
+    /// The durations that were played in the chosen mode
+    fn durations(&self) -> Vec<u16> {
+        let mut durations: Vec<u16> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.mode == self.mode.name())
+            .map(|entry| entry.seconds)
+            .collect();
+        durations.sort_unstable();
+        durations.dedup();
+        durations
+    }
+
+    fn uses_pack(&self) -> bool {
+        self.mode == GameMode::Words || self.mode == GameMode::Review
+    }
+
+    /// The pack the ranking is for, empty in modes that aren't typed on one
+    fn chosen_pack(&self) -> &str {
+        if self.uses_pack() {
+            &self.pack
+        } else {
+            ""
+        }
+    }
+
+    /// The packs that were played in the chosen mode and duration
+    fn packs(&self) -> Vec<String> {
+        let mut packs: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.mode == self.mode.name() && entry.seconds == self.seconds)
+            .map(|entry| entry.pack.clone())
+            .filter(|pack| !pack.is_empty())
+            .collect();
+        packs.sort();
+        packs.dedup();
+        packs
+    }
+
+    /// Every set of tags that was played with the chosen mode, duration and pack
+    fn tag_sets(&self) -> Vec<Vec<String>> {
+        let mut tag_sets: Vec<Vec<String>> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.mode == self.mode.name()
+                    && entry.seconds == self.seconds
+                    && entry.pack == self.chosen_pack()
+            })
+            .map(|entry| entry.sorted_tags())
+            .collect();
+        tag_sets.sort();
+        tag_sets.dedup();
+        tag_sets
+    }
+}
+
+pub fn draw_leaderboard_screen(
+    app_state: Res<State<AppState>>,
+    mut leaderboard: ResMut<Leaderboard>,
+    config: Res<UserConfig>,
+    mut ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+) {
+    if app_state.current() != &AppState::Leaderboard {
+        leaderboard.loaded = false;
+        return;
+    }
+
+    let directory = config.leaderboard.directory.trim();
+    if !leaderboard.loaded && !directory.is_empty() {
+        // Opens on the pack you type on yourself
+        leaderboard.pack = config.word_pack.clone();
+        leaderboard.load(directory);
+    }
+    leaderboard.receive();
+
+    let window = windows.get_primary().unwrap();
+
+    StyledCentralPanel::new(window.width(), config.theme)
+        .central_panel()
+        .show(ctx.ctx_mut(), |ui| {
+            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                ui.add(Label::new(
+                    RichText::new("LEADERBOARD").heading().color(Color32::GREEN),
+                ));
+                ui.add_space(40.);
+
+                if directory.is_empty() {
+                    ui.add(Label::new(
+                        RichText::new(
+                            "Pick a shared directory under Leaderboard on the SETTINGS screen",
+                        )
+                        .color(Color32::YELLOW),
+                    ));
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    for mode in ALL_GAME_MODES {
+                        ui.radio_value(&mut leaderboard.mode, mode, mode.name());
+                    }
+                });
+
+                let durations = leaderboard.durations();
+                ui.horizontal(|ui| {
+                    for seconds in durations {
+                        let text = match seconds {
+                            0 => "Untimed".to_string(),
+                            _ => format!("{} seconds", seconds),
+                        };
+                        ui.radio_value(&mut leaderboard.seconds, seconds, text);
+                    }
+                });
+
+                if leaderboard.uses_pack() {
+                    let packs = leaderboard.packs();
+                    ui.horizontal(|ui| {
+                        for pack in packs {
+                            let text = pack.clone();
+                            ui.radio_value(&mut leaderboard.pack, pack, text);
+                        }
+                    });
+                }
+
+                let tag_sets = leaderboard.tag_sets();
+                ui.horizontal(|ui| {
+                    for tags in tag_sets {
+                        let text = if tags.is_empty() {
+                            "No tags".to_string()
+                        } else {
+                            tags.join(", ")
+                        };
+                        ui.radio_value(&mut leaderboard.tags, tags, text);
+                    }
+                });
+
+                if ui.button("REFRESH").clicked() {
+                    leaderboard.load(directory);
+                }
+                if let Some(error) = &leaderboard.error {
+                    ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                }
+                ui.add_space(20.);
+
+                if leaderboard.loading.is_some() && leaderboard.entries.is_empty() {
+                    ui.add(Label::new(
+                        RichText::new("Loading...").color(Color32::YELLOW),
+                    ));
+                    return;
+                }
+
+                let standings = rank(
+                    &leaderboard.entries,
+                    leaderboard.mode.name(),
+                    leaderboard.seconds,
+                    leaderboard.chosen_pack(),
+                    &leaderboard.tags,
+                );
+                if standings.is_empty() {
+                    ui.add(Label::new(
+                        RichText::new("No results yet").color(Color32::YELLOW),
+                    ));
+                    return;
+                }
+
+                ScrollArea::vertical()
+                    .id_source("leaderboard")
+                    .max_height(RANKING_HEIGHT)
+                    .show(ui, |ui| {
+                        egui::Grid::new("leaderboard_ranking")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.label("");
+                                ui.label("Name");
+                                ui.label("Best WPM");
+                                ui.label("Accuracy");
+                                ui.label("Tests");
+                                ui.end_row();
+
+                                for (place, standing) in standings.iter().enumerate() {
+                                    ui.label(format!("{}.", place + 1));
+                                    ui.label(standing.name.as_str());
+                                    ui.label(standing.wpm.to_string());
+                                    ui.label(format!("{}%", standing.accuracy));
+                                    ui.label(standing.tests.to_string());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, wpm: u16, pack: &str, tags: &[&str]) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: name.to_string(),
+            timestamp: 0,
+            mode: GameMode::Words.name().to_string(),
+            seconds: 60,
+            wpm,
+            accuracy: 95,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            pack: pack.to_string(),
+        }
+    }
+
+    fn names(standings: &[Standing]) -> Vec<&str> {
+        standings
+            .iter()
+            .map(|standing| standing.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn ranks_each_players_best_run() {
+        let entries = vec![
+            entry("ada", 50, "english", &[]),
+            entry("bob", 70, "english", &[]),
+            entry("ada", 80, "english", &[]),
+        ];
+
+        let standings = rank(&entries, GameMode::Words.name(), 60, "english", &[]);
+        assert_eq!(names(&standings), ["ada", "bob"]);
+        assert_eq!(standings[0].wpm, 80);
+        assert_eq!(standings[0].tests, 2);
+    }
+
+    #[test]
+    fn only_ranks_runs_on_the_same_pack_with_the_same_tags() {
+        let entries = vec![
+            entry("ada", 50, "english", &[]),
+            entry("bob", 120, "english_200", &[]),
+            entry("cy", 110, "english", &["punctuation"]),
+            entry("dee", 100, "english", &["race"]),
+            entry("eve", 90, "english", &["punctuation", "numbers"]),
+        ];
+
+        assert_eq!(
+            names(&rank(&entries, GameMode::Words.name(), 60, "english", &[])),
+            ["ada"]
+        );
+        assert_eq!(
+            names(&rank(
+                &entries,
+                GameMode::Words.name(),
+                60,
+                "english",
+                &["punctuation".to_string()]
+            )),
+            ["cy"]
+        );
+        // Tags are compared in any order
+        let mut leaderboard = Leaderboard {
+            entries: entries.clone(),
+            pack: "english".to_string(),
+            ..Leaderboard::default()
+        };
+        let tag_sets = leaderboard.tag_sets();
+        assert_eq!(tag_sets.len(), 4);
+        leaderboard.tags = tag_sets[1].clone();
+        assert_eq!(leaderboard.tags, ["numbers", "punctuation"]);
+        assert_eq!(
+            names(&rank(
+                &entries,
+                GameMode::Words.name(),
+                60,
+                "english",
+                &leaderboard.tags
+            )),
+            ["eve"]
+        );
+    }
+
+    #[test]
+    fn the_lock_is_released_only_by_its_owner() {
+        let directory = tempfile::tempdir().unwrap();
+        let lock_path = directory.path().join(LOCK_DIRECTORY);
+
+        let lock = LeaderboardLock::acquire(directory.path()).unwrap();
+        assert_eq!(lock_owner(&lock_path), Some(lock.token.clone()));
+        drop(lock);
+        assert!(!lock_path.exists());
+
+        // Taken over by another app while this one was stuck
+        let lock = LeaderboardLock::acquire(directory.path()).unwrap();
+        fs::write(lock_path.join(LOCK_OWNER_FILE), "someone else").unwrap();
+        drop(lock);
+        assert_eq!(lock_owner(&lock_path).as_deref(), Some("someone else"));
+    }
+
+    #[test]
+    fn a_stale_lock_is_only_removed_while_it_is_still_the_one_found() {
+        let directory = tempfile::tempdir().unwrap();
+        let lock_path = directory.path().join(LOCK_DIRECTORY);
+        fs::create_dir(&lock_path).unwrap();
+        fs::write(lock_path.join(LOCK_OWNER_FILE), "new owner").unwrap();
+
+        // The stale lock was already replaced by a fresh one, which goes back in place
+        take_over(directory.path(), &lock_path, Some("old owner"), "mine");
+        assert_eq!(lock_owner(&lock_path).as_deref(), Some("new owner"));
+
+        take_over(directory.path(), &lock_path, Some("new owner"), "mine");
+        assert!(!lock_path.exists());
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 0);
+
+        // Someone else moved it aside first
+        take_over(directory.path(), &lock_path, Some("new owner"), "mine");
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn results_shared_at_the_same_time_all_end_up_in_the_file() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let directory = directory.path().to_path_buf();
+                thread::spawn(move || {
+                    for result in 0..5 {
+                        let name = format!("player {} result {}", writer, result);
+                        append_entry(&directory, &entry(&name, 60, "english", &[])).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let contents = fs::read_to_string(directory.path().join(LEADERBOARD_FILE)).unwrap();
+        let entries: Vec<LeaderboardEntry> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 40);
+        for writer in 0..8 {
+            for result in 0..5 {
+                let name = format!("player {} result {}", writer, result);
+                assert!(entries.iter().any(|entry| entry.name == name));
+            }
+        }
+        assert!(!directory.path().join(LOCK_DIRECTORY).exists());
+    }
+}
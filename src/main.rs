@@ -2,22 +2,69 @@ use bevy::prelude::*;
 use bevy::window::WindowResizeConstraints;
 use bevy_egui::egui::{Align, Color32, Label, Layout, RichText, TextEdit, TextStyle, Ui, Widget};
 use bevy_egui::{egui, EguiContext, EguiPlugin};
-use std::cmp::max;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 // USE
+use crate::book::BookState;
+use crate::code_snippets::CodeState;
+use crate::config::UserConfig;
 use crate::fonts::setup_fonts;
+use crate::game_mode::{GameMode, ALL_GAME_MODES};
+use crate::history::{History, TestResult};
+use crate::hotkeys::{map_hotkeys, Action, Rebinding, SidePanelFocus};
+use crate::key_stats::{get_digit_stats, get_symbol_stats, KeyStats};
+use crate::leaderboard::{draw_leaderboard_screen, share_result, Leaderboard};
+use crate::pace::{update_pace_caret, Ghost, GhostFinish, PaceCaret, PaceTarget};
+use crate::proctor::{verify_command, Proctor};
+use crate::race::{draw_race_screen, draw_race_standings, update_race, RaceClient, RACE_TAG};
+use crate::replay::{update_replay_viewer, Replay, ReplaySettings, ReplayViewer, PLAYBACK_SPEEDS};
+use crate::review::ReviewQueue;
+use crate::run_mode::{Failure, RunMode};
+use crate::screens::{draw_key_stats, draw_menu_screens};
+use crate::submission::{send_submissions, ResultSubmitter};
+use crate::text::{grapheme_count, graphemes, shaped_letters, visual_order};
 use crate::theme::Theme;
+use crate::transforms::{apply_pipeline, pipeline_pack_words};
 use crate::widgets::{
-    InputField, StyledButton, StyledCentralPanel, StyledSidePanel, WindowForLabels,
+    InputField, StyledButton, StyledCentralPanel, StyledComboBox, StyledSidePanel, WindowForLabels,
 };
-use crate::word_generator::{AllWords, PlayerWordList, WordList, WordListIndex};
+use crate::word_generator::{
+    get_number_drill_list, get_pack_word_list, get_symbol_drill_list, PlayerWordList, WordList,
+    WordListIndex, WORD_COUNT_PER_GAME,
+};
+use crate::word_packs::{TextDirection, WordPack, WordPacks};
 
 // MODULES
+mod book;
+mod code_snippets;
 mod colors;
+mod config;
 mod fonts;
+mod game_mode;
+mod history;
+mod hotkeys;
+mod inbox;
+mod key_stats;
+mod leaderboard;
+mod modifiers;
+mod pace;
+mod proctor;
+mod race;
+mod race_protocol;
+mod replay;
+mod result_posting;
+mod review;
+mod run_mode;
+mod screens;
+mod submission;
+mod text;
 mod theme;
+mod transforms;
 mod widgets;
+mod word_filters;
 mod word_generator;
+mod word_packs;
 
 // SETUP CONSTANTS
 const MINIMUM_WINDOW_WIDTH: f32 = 800.;
@@ -28,22 +75,41 @@ const HORZ_SPACE_BETWEEN_LABELS: f32 = 14.;
 
 const INPUT_SIZE: egui::Vec2 = egui::Vec2::new(240., 60.);
 
+const ROWS_SHOWN: usize = 4;
+const WORDS_PER_ROW: usize = 3;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     Menu,
     ReadyToPlay,
     Playing,
+    Paused,
     GameOver,
     FAQ,
+    Modes,
+    Settings,
+    /// A replay is being played back
+    Viewing,
+    /// The lobby of a race server
+    Race,
+    Leaderboard,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, StageLabel)]
 enum Stage {
+    DrawSidePanel,
     DrawPanels,
     UpdateTimer,
+    CheckGameOver,
 }
 
 fn main() {
+    // `verify FILE` checks a proctored result instead of opening the app
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("verify") {
+        std::process::exit(verify_command(&args[1..]));
+    }
+
     let mut app = App::new();
 
     app.add_state(AppState::Menu)
@@ -66,6 +132,11 @@ fn main() {
         // STAGES
         .add_stage_after(
             CoreStage::Update,
+            Stage::DrawSidePanel,
+            SystemStage::parallel(),
+        )
+        .add_stage_after(
+            Stage::DrawSidePanel,
             Stage::DrawPanels,
             SystemStage::parallel(),
         )
@@ -74,32 +145,114 @@ fn main() {
             Stage::UpdateTimer,
             SystemStage::parallel(),
         )
+        .add_stage_after(
+            Stage::UpdateTimer,
+            Stage::CheckGameOver,
+            SystemStage::parallel(),
+        )
         // STARTUP SYSTEMS
         .add_startup_system(setup)
-        .add_startup_system(setup_fonts)
+        // The fonts come from the word packs loaded in setup
+        .add_startup_system_to_stage(StartupStage::PostStartup, setup_fonts)
         // SYSTEMS
+        .add_event::<Action>()
+        .add_system(map_hotkeys)
+        .add_system(update_pace_caret)
+        .add_system(update_race)
+        .add_system(send_submissions)
+        .add_system_to_stage(Stage::DrawSidePanel, draw_side_panel)
         .add_system_to_stage(Stage::DrawPanels, draw_ui)
+        .add_system_to_stage(Stage::DrawPanels, draw_menu_screens)
+        .add_system_to_stage(Stage::DrawPanels, draw_race_screen)
+        .add_system_to_stage(Stage::DrawPanels, draw_race_standings)
+        .add_system_to_stage(Stage::DrawPanels, draw_leaderboard_screen)
         .add_system_to_stage(Stage::UpdateTimer, update_game_timer)
+        .add_system_to_stage(Stage::UpdateTimer, extend_word_list)
+        .add_system_to_stage(Stage::UpdateTimer, update_replay_viewer)
+        .add_system_to_stage(Stage::CheckGameOver, end_game)
         .run();
 }
 
 fn setup(mut commands: Commands, mut ctx: ResMut<EguiContext>) {
-    ctx.ctx_mut().set_visuals(Theme::new().visuals().clone());
-
     commands.insert_resource(InputField {
         text: String::from(""),
         enabled: false,
     });
 
+    commands.insert_resource(Rebinding::default());
+    commands.insert_resource(SidePanelFocus(None));
+    commands.insert_resource(PaceCaret::default());
+    commands.insert_resource(ReplayViewer::default());
+    commands.insert_resource(RaceClient::default());
+    commands.insert_resource(Leaderboard::default());
+
     commands.insert_resource(GeneralTimer(Timer::from_seconds(1.0, true)));
-    commands.insert_resource(GameTimer(0));
+    commands.insert_resource(GameTimer::new());
 
-    create_new_word_list(&mut commands);
+    // A proctored test runs on fixed settings instead of the player's own
+    let proctor = Proctor::load();
+    let mut config = UserConfig::load();
+    if let Some(proctor_config) = &proctor.config {
+        config = proctor_config.user_config(config.theme);
+    }
+    commands.insert_resource(proctor);
+
+    ctx.ctx_mut()
+        .set_visuals(Theme::new(config.theme).visuals().clone());
+
+    let game_mode = GameMode::Words;
+    let run_mode = RunMode::Timed;
+    let book_state = BookState::new();
+    let code_state = CodeState::new(&config.code_source);
+    let word_packs = WordPacks::load();
+    let review_queue = ReviewQueue::load();
+
+    create_new_word_list(
+        &mut commands,
+        &game_mode,
+        word_packs.get(&config.word_pack),
+        &book_state,
+        &code_state,
+        &review_queue,
+        &config,
+    );
+
+    commands.insert_resource(History::load());
+    commands.insert_resource(ResultSubmitter::load());
+    commands.insert_resource(word_packs);
+    commands.insert_resource(review_queue);
+    commands.insert_resource(config);
+    commands.insert_resource(game_mode);
+    commands.insert_resource(run_mode);
+    commands.insert_resource(book_state);
+    commands.insert_resource(code_state);
 }
 
 struct GeneralTimer(Timer);
 
-struct GameTimer(u8);
+/// Counts the seconds played; the run mode decides whether there is a limit
+pub struct GameTimer {
+    /// Zen runs have no time limit, so this can go well past what a u16 holds
+    pub elapsed_seconds: u32,
+    stop_requested: bool,
+    was_paused: bool,
+}
+
+impl GameTimer {
+    fn new() -> Self {
+        Self {
+            elapsed_seconds: 0,
+            stop_requested: false,
+            was_paused: false,
+        }
+    }
+
+    fn seconds_left(&self, run_mode: &RunMode) -> Option<u16> {
+        run_mode
+            .duration()
+            .map(|duration| (duration as u32).saturating_sub(self.elapsed_seconds) as u16)
+    }
+}
 
 fn update_game_timer(
     time: Res<Time>,
@@ -107,63 +260,681 @@ fn update_game_timer(
     mut game_timer: ResMut<GameTimer>,
     app_state: Res<State<AppState>>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        if app_state.current() == &AppState::Playing {
-            if game_timer.0 > 0 {
-                game_timer.0 -= 1;
-            }
+    // The clock only runs while playing, so a pause freezes it mid-second
+    if app_state.current() == &AppState::Playing && timer.0.tick(time.delta()).just_finished() {
+        game_timer.elapsed_seconds += 1;
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SidePanelButton {
+    Start,
+    Pause,
+    Modes,
+    Race,
+    Leaderboard,
+    Settings,
+    Faq,
+}
+
+impl SidePanelButton {
+    fn text(&self, app_state: &AppState, replay_playing: bool) -> &'static str {
+        match self {
+            SidePanelButton::Start => "START",
+            SidePanelButton::Pause if app_state == &AppState::Paused => "RESUME",
+            SidePanelButton::Pause if app_state == &AppState::Viewing && !replay_playing => "PLAY",
+            SidePanelButton::Pause => "PAUSE",
+            SidePanelButton::Modes => "MODES",
+            SidePanelButton::Race => "RACE",
+            SidePanelButton::Leaderboard => "LEADERBOARD",
+            SidePanelButton::Settings => "SETTINGS",
+            SidePanelButton::Faq => "FAQ",
         }
     }
 }
 
-fn draw_ui(
+fn toggle_pause(
+    app_state: &mut State<AppState>,
+    game_timer: &mut GameTimer,
+    player_word_list: &mut PlayerWordList,
+) {
+    if app_state.current() == &AppState::Playing {
+        if app_state.set(AppState::Paused).is_ok() {
+            game_timer.was_paused = true;
+            player_word_list.pause_timing();
+        }
+    } else if app_state.current() == &AppState::Paused && app_state.set(AppState::Playing).is_ok() {
+        player_word_list.resume_timing();
+    }
+}
+
+/// Aborted tests go straight back to the menu and are never recorded
+fn abort_test(app_state: &mut State<AppState>, input_text: &mut InputField) {
+    let running = app_state.current() == &AppState::Playing
+        || app_state.current() == &AppState::Paused
+        || app_state.current() == &AppState::ReadyToPlay;
+
+    if running && app_state.set(AppState::Menu).is_ok() {
+        input_text.text = "".to_string();
+        input_text.enabled = false;
+    }
+}
+
+/// Untimed tests get more words before the player runs out of them
+#[allow(clippy::too_many_arguments)]
+fn extend_word_list(
+    app_state: Res<State<AppState>>,
+    run_mode: Res<RunMode>,
+    game_mode: Res<GameMode>,
+    mut word_list: ResMut<WordList>,
+    word_list_index: Res<WordListIndex>,
+    word_packs: Res<WordPacks>,
+    book_state: Res<BookState>,
+    code_state: Res<CodeState>,
+    review_queue: Res<ReviewQueue>,
+    config: Res<UserConfig>,
+) {
+    if app_state.current() != &AppState::Playing || run_mode.duration().is_some() {
+        return;
+    }
+
+    // A book page and the review queue end when their words do
+    if *game_mode == GameMode::Book || *game_mode == GameMode::Review {
+        return;
+    }
+
+    if word_list_index.current_index + ROWS_SHOWN * WORDS_PER_ROW >= word_list.list.len() {
+        // Seeded by how far the list got, so the same seed still always gives the same words
+        let seed = word_list.seed.wrapping_add(word_list.list.len() as u64);
+        let more_words = generate_word_list(
+            &game_mode,
+            word_packs.get(&config.word_pack),
+            &book_state,
+            &code_state,
+            &review_queue,
+            &config,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        word_list.list.extend(more_words);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn end_game(
     mut commands: Commands,
     mut app_state: ResMut<State<AppState>>,
-    mut input_text: ResMut<InputField>,
     word_list: Res<WordList>,
-    mut player_word_list: ResMut<PlayerWordList>,
-    mut word_list_index: ResMut<WordListIndex>,
+    player_word_list: Res<PlayerWordList>,
+    word_list_index: Res<WordListIndex>,
     game_timer: Res<GameTimer>,
+    run_mode: Res<RunMode>,
+    mut game_mode: ResMut<GameMode>,
+    mut book_state: ResMut<BookState>,
+    word_packs: Res<WordPacks>,
+    mut review_queue: ResMut<ReviewQueue>,
+    config: Res<UserConfig>,
+    ghost: Res<Ghost>,
+    text_direction: Res<TextDirection>,
+    mut history: ResMut<History>,
+    (mut submitter, mut proctor): (ResMut<ResultSubmitter>, ResMut<Proctor>),
+) {
+    if app_state.current() != &AppState::Playing {
+        return;
+    }
+
+    let time_is_up = game_timer.seconds_left(&run_mode) == Some(0);
+
+    // Stats over a minute are the plain counts, which is all the accuracy check needs; they go
+    // over every word typed, so they are left out when the mode can't fail anyway
+    let failure = if run_mode.can_fail() {
+        let running_stats = get_game_stats(&word_list.list, &player_word_list.list, 60);
+        run_mode.check_failure(
+            &word_list.list,
+            &player_word_list.list,
+            running_stats.accuracy(),
+            running_stats.cpm,
+        )
+    } else {
+        None
+    };
+
+    // A book page can run out of words before the time does
+    if !time_is_up
+        && !game_timer.stop_requested
+        && failure.is_none()
+        && word_list_index.current_index < word_list.list.len()
+    {
+        return;
+    }
+
+    // Another transition was already requested this frame, such as a restart
+    if app_state.set(AppState::GameOver).is_err() {
+        return;
+    }
+
+    let seconds_played = game_timer.elapsed_seconds.max(1);
+    let mut game_stats = get_game_stats(&word_list.list, &player_word_list.list, seconds_played);
+
+    // Words from a ghost or a race aren't from the book or the review queue
+    let shared_words = word_list.tag.is_some();
+
+    if *game_mode == GameMode::Book && !shared_words {
+        book_state.record_session(&word_list.list, &player_word_list.list, seconds_played);
+    }
+
+    if (*game_mode == GameMode::Words || *game_mode == GameMode::Review) && !shared_words {
+        if let Some(word_pack) = word_packs.get(&config.word_pack) {
+            review_queue.record_test(
+                word_pack,
+                &word_list.list,
+                &player_word_list.list,
+                &player_word_list.word_seconds,
+            );
+        }
+    }
+
+    // Untimed runs are stored with a duration of 0 so they are compared with each other
+    let mut result = TestResult::new(
+        game_mode.name(),
+        run_mode.duration().unwrap_or(0),
+        game_stats.cpm,
+        game_stats.correct_cpm,
+        game_stats.wpm,
+    );
+    // A race is typed on the server's pack without the player's own settings, so it is
+    // only compared with other races
+    if word_list.tag.as_deref() == Some(RACE_TAG) {
+        result.tags = vec![RACE_TAG.to_string()];
+    } else {
+        result.tags = config.result_tags(&game_mode, &run_mode, word_packs.get(&config.word_pack));
+        result.tags.extend(word_list.tag.clone());
+    }
+    result.failed = failure.is_some();
+    result.paused = game_timer.was_paused;
+    if *game_mode == GameMode::Numbers {
+        game_stats.key_stats = get_digit_stats(&word_list.list, &player_word_list.list);
+        result.key_stats = game_stats.key_stats.clone();
+    }
+    if *game_mode == GameMode::Symbols {
+        game_stats.key_stats = get_symbol_stats(&word_list.list, &player_word_list.list);
+        result.key_stats = game_stats.key_stats.clone();
+    }
+
+    // A failed or paused run can't be a new best, so it isn't compared
+    if failure.is_none() && !game_timer.was_paused {
+        game_stats.previous_best_wpm = history.best_wpm(&result);
+    }
+    game_stats.failure = failure;
+
+    let word_pack_id = word_list
+        .pack
+        .clone()
+        .unwrap_or_else(|| config.word_pack.clone());
+
+    // Failed and paused runs stay off the team's leaderboard like they stay off your best
+    if !result.failed && !result.paused {
+        let shared_pack = match *game_mode {
+            GameMode::Words | GameMode::Review => word_pack_id.as_str(),
+            _ => "",
+        };
+        share_result(
+            &config.leaderboard,
+            &result,
+            game_stats.accuracy(),
+            shared_pack,
+        );
+    }
+    submitter.submit(&config, &result, game_stats.accuracy());
+
+    proctor.sign_attempt(
+        &word_list,
+        &player_word_list.keystrokes,
+        seconds_played,
+        &game_stats,
+        false,
+    );
+
+    let replay_settings = ReplaySettings {
+        word_pack: word_pack_id,
+        direction: *text_direction,
+        seconds: result.seconds,
+        tags: result.tags.clone(),
+    };
+    history.record(result);
+
+    Replay::new(
+        game_mode.name(),
+        game_stats.wpm,
+        &word_list,
+        replay_settings,
+        &player_word_list.keystrokes,
+    )
+    .save();
+
+    if let Some(replay) = &ghost.replay {
+        let (ghost_words, _) = replay.position_at(player_word_list.elapsed_seconds());
+
+        game_stats.ghost_finish = Some(GhostFinish {
+            wpm: replay.wpm,
+            words_ahead: player_word_list.list.len() as i64 - ghost_words as i64,
+        });
+    }
+    game_stats.all_time_key_stats = history.all_time_key_stats(game_mode.name());
+
+    // There's nothing left to review once the queue is drained
+    if *game_mode == GameMode::Review {
+        let pack_id = word_packs
+            .get(&config.word_pack)
+            .map(|word_pack| word_pack.id.clone())
+            .unwrap_or_default();
+
+        if review_queue.due_words(&pack_id).is_empty() {
+            *game_mode = GameMode::Words;
+        }
+    }
+
+    commands.insert_resource(game_stats);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_side_panel(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    mut input_text: ResMut<InputField>,
+    mut game_mode: ResMut<GameMode>,
+    book_state: Res<BookState>,
+    code_state: Res<CodeState>,
+    word_packs: Res<WordPacks>,
+    review_queue: Res<ReviewQueue>,
     final_game_stats: Option<Res<GameStats>>,
-    mut ctx: ResMut<EguiContext>,
-    mut windows: ResMut<Windows>,
+    mut game_timer: ResMut<GameTimer>,
+    mut player_word_list: ResMut<PlayerWordList>,
+    mut config: ResMut<UserConfig>,
+    mut actions: EventReader<Action>,
+    mut focus: ResMut<SidePanelFocus>,
+    mut viewer: ResMut<ReplayViewer>,
+    (word_list, mut proctor, mut ctx): (Res<WordList>, ResMut<Proctor>, ResMut<EguiContext>),
 ) {
-    let input_enabled = input_text.enabled;
-    let input_empty = input_text.text.is_empty();
-    let mut move_index_by = 0;
+    let actions: Vec<Action> = actions.iter().copied().collect();
 
-    let window = windows.get_primary_mut().unwrap();
+    let mut buttons = vec![SidePanelButton::Start];
+    // A proctored test can't be paused, and its settings can't be changed
+    if config.locked {
+        buttons.push(SidePanelButton::Faq);
+    } else {
+        if app_state.current() == &AppState::Playing
+            || app_state.current() == &AppState::Paused
+            || app_state.current() == &AppState::Viewing
+        {
+            buttons.push(SidePanelButton::Pause);
+        }
+        buttons.extend([
+            SidePanelButton::Modes,
+            SidePanelButton::Race,
+            SidePanelButton::Leaderboard,
+            SidePanelButton::Settings,
+            SidePanelButton::Faq,
+        ]);
+    }
+
+    for action in &actions {
+        focus.0 = match (action, focus.0) {
+            (Action::FocusUp, Some(index)) => Some((index + buttons.len() - 1) % buttons.len()),
+            (Action::FocusDown, Some(index)) => Some((index + 1) % buttons.len()),
+            (Action::FocusUp, None) => Some(buttons.len() - 1),
+            (Action::FocusDown, None) => Some(0),
+            _ => focus.0,
+        };
+    }
+    // The pause button goes away when a test ends
+    if let Some(index) = focus.0 {
+        focus.0 = Some(index.min(buttons.len() - 1));
+    }
+
+    // These have no button of their own
+    let testing =
+        app_state.current() == &AppState::Playing || app_state.current() == &AppState::Paused;
+    for action in &actions {
+        match action {
+            Action::OpenStats
+                if !testing
+                    && final_game_stats.is_some()
+                    && app_state.current() != &AppState::GameOver =>
+            {
+                app_state.set(AppState::GameOver).unwrap();
+            }
+            Action::SwitchMode if !testing && !config.locked => {
+                let word_pack = word_packs.get(&config.word_pack);
+                *game_mode = next_game_mode(*game_mode, &book_state, &code_state, || {
+                    word_pack
+                        .map(|word_pack| !review_queue.due_words(&word_pack.id).is_empty())
+                        .unwrap_or(false)
+                });
 
-    StyledSidePanel::new()
+                if app_state.current() == &AppState::ReadyToPlay {
+                    create_new_word_list(
+                        &mut commands,
+                        &game_mode,
+                        word_pack,
+                        &book_state,
+                        &code_state,
+                        &review_queue,
+                        &config,
+                    );
+                }
+            }
+            Action::ToggleTheme => {
+                config.theme = config.theme.toggled();
+                config.save();
+                ctx.ctx_mut()
+                    .set_visuals(Theme::new(config.theme).visuals().clone());
+            }
+            _ => {}
+        }
+    }
+
+    StyledSidePanel::new(config.theme)
         .side_panel()
         .show(ctx.ctx_mut(), |ui| {
             ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
-                let button_start = StyledButton::new("START").ui(ui);
-                if button_start.clicked() {
-                    if app_state.current() != &AppState::ReadyToPlay {
-                        app_state.set(AppState::ReadyToPlay).unwrap();
-                        commands.insert_resource(GameTimer(60));
-                        create_new_word_list(&mut commands);
+                let mut pressed = None;
+
+                for (index, button) in buttons.iter().enumerate() {
+                    let clicked =
+                        StyledButton::new(button.text(app_state.current(), viewer.playing))
+                            .focused(focus.0 == Some(index))
+                            .ui(ui)
+                            .clicked();
+
+                    if clicked {
+                        pressed = Some(*button);
+                    }
+                }
+
+                // Hotkeys press the same buttons a click would
+                for action in &actions {
+                    match action {
+                        Action::Restart => pressed = Some(SidePanelButton::Start),
+                        Action::Settings if buttons.contains(&SidePanelButton::Settings) => {
+                            pressed = Some(SidePanelButton::Settings)
+                        }
+                        Action::Pause if buttons.contains(&SidePanelButton::Pause) => {
+                            pressed = Some(SidePanelButton::Pause)
+                        }
+                        Action::Activate => {
+                            if let Some(index) = focus.0 {
+                                pressed = Some(buttons[index]);
+                            }
+                        }
+                        Action::Menu => {
+                            proctor.abort_attempt(
+                                &word_list,
+                                &player_word_list,
+                                game_timer.elapsed_seconds,
+                            );
+                            abort_test(&mut app_state, &mut input_text);
+
+                            if app_state.current() != &AppState::Menu {
+                                let _ = app_state.set(AppState::Menu);
+                            }
+                        }
+                        _ => {}
                     }
-                    input_text.text = "".to_string();
-                    input_text.enabled = true;
                 }
 
-                let button_new = StyledButton::new("FAQ").ui(ui);
-                if button_new.clicked() {
-                    if app_state.current() != &AppState::FAQ {
+                match pressed {
+                    Some(SidePanelButton::Start) => {
+                        if app_state.current() != &AppState::ReadyToPlay {
+                            app_state.set(AppState::ReadyToPlay).unwrap();
+                            proctor.start_attempt(
+                                &word_list,
+                                &player_word_list,
+                                game_timer.elapsed_seconds,
+                            );
+                            commands.insert_resource(GameTimer::new());
+                            create_new_word_list(
+                                &mut commands,
+                                &game_mode,
+                                word_packs.get(&config.word_pack),
+                                &book_state,
+                                &code_state,
+                                &review_queue,
+                                &config,
+                            );
+                        }
+                        input_text.text = "".to_string();
+                        input_text.enabled = true;
+
+                        // Enter is for typing again once a test is under way
+                        focus.0 = None;
+                    }
+                    // The same button plays and pauses a replay, and starts it over once it ended
+                    Some(SidePanelButton::Pause) if app_state.current() == &AppState::Viewing => {
+                        let ended = viewer
+                            .replay
+                            .as_ref()
+                            .is_some_and(|replay| viewer.seconds >= replay.duration());
+
+                        if ended {
+                            viewer.rewind();
+                        } else {
+                            viewer.playing = !viewer.playing;
+                        }
+                    }
+                    Some(SidePanelButton::Pause) => {
+                        toggle_pause(&mut app_state, &mut game_timer, &mut player_word_list);
+                    }
+                    Some(SidePanelButton::Modes) if app_state.current() != &AppState::Modes => {
+                        app_state.set(AppState::Modes).unwrap();
+                    }
+                    Some(SidePanelButton::Race) if app_state.current() != &AppState::Race => {
+                        app_state.set(AppState::Race).unwrap();
+                        input_text.text = "".to_string();
+                        input_text.enabled = false;
+                    }
+                    Some(SidePanelButton::Leaderboard)
+                        if app_state.current() != &AppState::Leaderboard =>
+                    {
+                        app_state.set(AppState::Leaderboard).unwrap();
+                    }
+                    Some(SidePanelButton::Settings)
+                        if app_state.current() != &AppState::Settings =>
+                    {
+                        app_state.set(AppState::Settings).unwrap();
+                    }
+                    Some(SidePanelButton::Faq) if app_state.current() != &AppState::FAQ => {
                         app_state.set(AppState::FAQ).unwrap();
                     }
+                    _ => {}
+                }
+
+                if app_state.current() == &AppState::Viewing {
+                    draw_replay_controls(ui, &mut viewer);
+                }
+
+                ui.add_space(30.);
+                ui.label("Language");
+
+                let selected_pack = match word_packs.get(&config.word_pack) {
+                    Some(selected_pack) => selected_pack,
+                    None => {
+                        ui.label("No word packs");
+                        return;
+                    }
+                };
+                if config.locked {
+                    ui.label(selected_pack.display_name());
+                    return;
+                }
+                let mut chosen_pack = selected_pack.id.clone();
+
+                StyledComboBox::new("word_pack", selected_pack.display_name())
+                    .combo_box()
+                    .show_ui(ui, |ui| {
+                        for pack in &word_packs.packs {
+                            ui.selectable_value(
+                                &mut chosen_pack,
+                                pack.id.clone(),
+                                pack.display_name(),
+                            )
+                            .on_hover_text(pack.description());
+                        }
+                    })
+                    .response
+                    .on_hover_text(selected_pack.description());
+
+                if chosen_pack != selected_pack.id {
+                    config.word_pack = chosen_pack;
+                    config.save();
+
+                    // Swap the words right away if the test hasn't started yet
+                    if app_state.current() == &AppState::ReadyToPlay {
+                        create_new_word_list(
+                            &mut commands,
+                            &game_mode,
+                            word_packs.get(&config.word_pack),
+                            &book_state,
+                            &code_state,
+                            &review_queue,
+                            &config,
+                        );
+                    }
                 }
             });
         });
+}
+
+fn draw_replay_controls(ui: &mut Ui, viewer: &mut ReplayViewer) {
+    let replay = match &viewer.replay {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    ui.add_space(30.);
+    ui.label(replay.description());
+    ui.label(format!(
+        "{}:{:02} / {}:{:02}",
+        viewer.seconds as u32 / 60,
+        viewer.seconds as u32 % 60,
+        replay.duration() as u32 / 60,
+        replay.duration() as u32 % 60
+    ));
+
+    ui.horizontal(|ui| {
+        for speed in PLAYBACK_SPEEDS {
+            if ui
+                .radio(viewer.speed == speed, format!("{}x", speed))
+                .clicked()
+            {
+                viewer.speed = speed;
+            }
+        }
+    });
+}
+
+/// The mode after the current one that can be played right now
+fn next_game_mode(
+    current: GameMode,
+    book_state: &BookState,
+    code_state: &CodeState,
+    has_due_words: impl Fn() -> bool,
+) -> GameMode {
+    let position = ALL_GAME_MODES
+        .iter()
+        .position(|mode| *mode == current)
+        .unwrap_or(0);
+
+    (1..ALL_GAME_MODES.len())
+        .map(|offset| ALL_GAME_MODES[(position + offset) % ALL_GAME_MODES.len()])
+        .find(|mode| match mode {
+            GameMode::Book => book_state.book().is_some(),
+            GameMode::Code => !code_state.snippets().is_empty(),
+            GameMode::Review => has_due_words(),
+            _ => true,
+        })
+        .unwrap_or(current)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_ui(
+    mut app_state: ResMut<State<AppState>>,
+    mut input_text: ResMut<InputField>,
+    word_list: Res<WordList>,
+    mut player_word_list: ResMut<PlayerWordList>,
+    mut word_list_index: ResMut<WordListIndex>,
+    mut game_timer: ResMut<GameTimer>,
+    run_mode: Res<RunMode>,
+    final_game_stats: Option<Res<GameStats>>,
+    game_mode: Res<GameMode>,
+    book_state: Res<BookState>,
+    word_packs: Res<WordPacks>,
+    text_direction: Res<TextDirection>,
+    (pace_caret, submitter, proctor): (Res<PaceCaret>, Res<ResultSubmitter>, Res<Proctor>),
+    config: Res<UserConfig>,
+    mut ctx: ResMut<EguiContext>,
+    mut windows: ResMut<Windows>,
+) {
+    // The modes, settings, race and leaderboard screens are drawn by their own systems
+    if app_state.current() == &AppState::Modes
+        || app_state.current() == &AppState::Settings
+        || app_state.current() == &AppState::Race
+        || app_state.current() == &AppState::Leaderboard
+    {
+        return;
+    }
+
+    let input_enabled = input_text.enabled;
+    let input_empty = input_text.text.is_empty();
+    let previous_text = input_text.text.clone();
+    let mut move_index_by = 0;
+    let mut test_started = false;
+
+    let window = windows.get_primary_mut().unwrap();
 
-    StyledCentralPanel::new(window.width())
+    StyledCentralPanel::new(window.width(), config.theme)
         .central_panel()
         .show(ctx.ctx_mut(), |ui| {
             ui.with_layout(Layout::top_down(Align::Center), |ui| {
                 if app_state.current() == &AppState::Menu {
                     ui.heading("Press Start");
+
+                    if let Some(proctor_config) = &proctor.config {
+                        ui.add_space(30.);
+                        ui.add(Label::new(
+                            RichText::new(format!(
+                                "Proctored test for {}: the settings are locked and results are signed",
+                                proctor_config.candidate
+                            ))
+                            .color(Color32::YELLOW),
+                        ));
+                    }
+                    if let Some(error) = &proctor.error {
+                        ui.add_space(30.);
+                        ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                    }
+
+                    // Broken packs are left out of the list, this says why
+                    if !word_packs.errors.is_empty() {
+                        ui.add_space(30.);
+                        for error in &word_packs.errors {
+                            ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                        }
+                    }
+                    return;
+                } else if app_state.current() == &AppState::Paused {
+                    // The words stay hidden so a pause can't be used to read ahead
+                    ui.add(Label::new(
+                        RichText::new("PAUSED").heading().color(Color32::GREEN),
+                    ));
+                    ui.add_space(30.);
+                    ui.add(Label::new(
+                        RichText::new(format!(
+                            "{} or RESUME to continue, {} to abort",
+                            config.key_bindings.get(Action::Pause).text(),
+                            config.key_bindings.get(Action::Menu).text()
+                        ))
+                        .color(Color32::YELLOW),
+                    ));
                     return;
                 } else if app_state.current() == &AppState::FAQ {
                     ui.add(Label::new(
@@ -195,9 +966,23 @@ fn draw_ui(
                     return;
                 } else if app_state.current() == &AppState::GameOver {
                     if let Some(final_game_stats) = final_game_stats {
-                        ui.add(Label::new(
-                            RichText::new("TIMES UP").heading().color(Color32::GREEN),
-                        ));
+                        if let Some(failure) = &final_game_stats.failure {
+                            ui.add(Label::new(
+                                RichText::new("FAILED").heading().color(Color32::RED),
+                            ));
+                            ui.add(Label::new(
+                                RichText::new(failure.description()).color(Color32::YELLOW),
+                            ));
+                        } else {
+                            let heading = if run_mode.duration().is_some() {
+                                "TIMES UP"
+                            } else {
+                                "STOPPED"
+                            };
+                            ui.add(Label::new(
+                                RichText::new(heading).heading().color(Color32::GREEN),
+                            ));
+                        }
                         ui.add_space(60.);
 
                         ui.heading("CPM");
@@ -218,19 +1003,133 @@ fn draw_ui(
                             RichText::new(final_game_stats.wpm.to_string()).color(Color32::YELLOW),
                         ));
                         ui.add_space(30.);
+
+                        // Only runs with the same mode and modifiers count towards the best
+                        if let Some(previous_best_wpm) = final_game_stats.previous_best_wpm {
+                            ui.heading("Best WPM");
+                            if final_game_stats.wpm > previous_best_wpm {
+                                ui.add(Label::new(RichText::new("NEW BEST").color(Color32::GREEN)));
+                            } else {
+                                ui.add(Label::new(
+                                    RichText::new(previous_best_wpm.to_string())
+                                        .color(Color32::YELLOW),
+                                ));
+                            }
+                        }
+
+                        if let Some(ghost_finish) = &final_game_stats.ghost_finish {
+                            ui.add_space(30.);
+                            ui.heading("Ghost WPM");
+                            ui.add(Label::new(
+                                RichText::new(ghost_finish.wpm.to_string()).color(Color32::YELLOW),
+                            ));
+
+                            let (text, color) = match ghost_finish.words_ahead {
+                                0 => ("TIED WITH THE GHOST".to_string(), Color32::YELLOW),
+                                1..=i64::MAX => (
+                                    format!("YOU WON BY {} WORDS", ghost_finish.words_ahead),
+                                    Color32::GREEN,
+                                ),
+                                _ => (
+                                    format!("THE GHOST WON BY {} WORDS", -ghost_finish.words_ahead),
+                                    Color32::RED,
+                                ),
+                            };
+                            ui.add(Label::new(RichText::new(text).color(color)));
+                        }
+
+                        match &proctor.saved {
+                            Some(Ok(path)) => {
+                                ui.add_space(30.);
+                                ui.add(Label::new(
+                                    RichText::new(format!("Signed result saved to {}", path))
+                                        .color(Color32::GREEN),
+                                ));
+                            }
+                            Some(Err(error)) => {
+                                ui.add_space(30.);
+                                ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                            }
+                            None => {}
+                        }
+
+                        if let Some(status) = &submitter.status {
+                            ui.add_space(30.);
+                            ui.add(Label::new(RichText::new(status).color(Color32::GRAY)));
+                        }
+
+                        if !final_game_stats.key_stats.is_empty() {
+                            ui.add_space(30.);
+                            draw_key_stats(
+                                ui,
+                                &final_game_stats.key_stats,
+                                &final_game_stats.all_time_key_stats,
+                            );
+                        }
                     }
                     return;
                 } else if app_state.current() == &AppState::Playing {
-                    ui.heading(game_timer.0.to_string());
-
-                    if game_timer.0 == 0 {
-                        let game_stats = get_game_stats(&word_list.list, &player_word_list.list);
-                        commands.insert_resource(game_stats);
-                        app_state.set(AppState::GameOver).unwrap();
-                        return;
+                    match game_timer.seconds_left(&run_mode) {
+                        Some(seconds_left) => {
+                            ui.heading(seconds_left.to_string());
+                        }
+                        // Without a countdown the running stats take its place
+                        None => {
+                            let live_stats = get_game_stats(
+                                &word_list.list,
+                                &player_word_list.list,
+                                game_timer.elapsed_seconds.max(1),
+                            );
+                            ui.heading(format!(
+                                "{} WPM {}%",
+                                live_stats.wpm,
+                                live_stats.accuracy()
+                            ));
+                            ui.add(Label::new(
+                                RichText::new(format!(
+                                    "{}:{:02} - Press Enter to stop",
+                                    game_timer.elapsed_seconds / 60,
+                                    game_timer.elapsed_seconds % 60
+                                ))
+                                .color(Color32::YELLOW),
+                            ));
+                        }
                     }
                 } else if app_state.current() == &AppState::ReadyToPlay {
                     ui.heading("Type to Begin");
+                } else if app_state.current() == &AppState::Viewing {
+                    ui.add(Label::new(
+                        RichText::new("REPLAY").heading().color(Color32::GREEN),
+                    ));
+                    ui.add(Label::new(
+                        RichText::new(format!(
+                            "{}:{:02}",
+                            game_timer.elapsed_seconds / 60,
+                            game_timer.elapsed_seconds % 60
+                        ))
+                        .color(Color32::YELLOW),
+                    ));
+                }
+
+                if let Some(error) = &pace_caret.error {
+                    ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                }
+                if let Some(pace_label) = &pace_caret.label {
+                    let words_ahead = pace_caret.words_ahead(word_list_index.current_index);
+                    let (text, color) = match words_ahead {
+                        0 => ("on pace".to_string(), Color32::YELLOW),
+                        1..=i64::MAX => (format!("{} ahead", words_ahead), Color32::GREEN),
+                        _ => (format!("{} behind", -words_ahead), Color32::RED),
+                    };
+                    ui.add(Label::new(
+                        RichText::new(format!("{}: {}", pace_label, text)).color(color),
+                    ));
+                }
+
+                if *game_mode == GameMode::Book {
+                    ui.add(Label::new(
+                        RichText::new(book_state.position_text()).color(Color32::YELLOW),
+                    ));
                 }
 
                 ui.add_space(20.);
@@ -247,6 +1146,12 @@ fn draw_ui(
                     input.request_focus();
                 }
 
+                let current_word = word_list
+                    .list
+                    .get(word_list_index.current_index)
+                    .cloned()
+                    .unwrap_or_default();
+
                 if input.changed() && ui.input().key_pressed(egui::Key::Space) {
                     // If the game hasn't started - ignore spaces
                     if app_state.current() == &AppState::ReadyToPlay {
@@ -254,7 +1159,12 @@ fn draw_ui(
                     }
                     // If space is pressed and the game has started; move to the next word
                     else if app_state.current() == &AppState::Playing {
-                        move_index_by = 1;
+                        // Strict mode refuses to move on until the word is right
+                        if config.strict_mode && input_text.text.trim() != current_word {
+                            input_text.text = input_text.text.replace(' ', "");
+                        } else {
+                            move_index_by = 1;
+                        }
                     }
                 }
                 // Load previous input contents on backspace
@@ -267,11 +1177,25 @@ fn draw_ui(
                 }
                 // Check if the letter typed is the correct next letter
                 else if input.changed() {
+                    // Wrong letters never make it into the input when stopping on letters
+                    if config.stop_on_letter && !current_word.starts_with(input_text.text.as_str())
+                    {
+                        input_text.text = previous_text.clone();
+                    }
                     // Start the game
-                    if app_state.current() == &AppState::ReadyToPlay {
+                    else if app_state.current() == &AppState::ReadyToPlay {
                         app_state.set(AppState::Playing).unwrap();
+                        player_word_list.start_timing();
+                        test_started = true;
                     }
                 }
+                // Untimed tests only end when the player says so
+                if app_state.current() == &AppState::Playing
+                    && run_mode.duration().is_none()
+                    && ui.input().key_pressed(egui::Key::Enter)
+                {
+                    game_timer.stop_requested = true;
+                }
                 // To make sure the focus is always on the input
                 if input.lost_focus() {
                     input.request_focus();
@@ -282,8 +1206,8 @@ fn draw_ui(
                 // Used to know where to position the window as windows float and default to 0, 0
                 let end_point = ui.label("");
 
-                let rows: usize = 4;
-                let words_per_row: usize = 3;
+                let rows: usize = ROWS_SHOWN;
+                let words_per_row: usize = WORDS_PER_ROW;
 
                 let mut available_line_widths = Vec::<f32>::new();
 
@@ -293,13 +1217,14 @@ fn draw_ui(
                     3000.0, //Arbitrary numbers off-screen
                     3000.0,
                 )
+                .window()
                 .show(ui.ctx(), |ui| {
                     // To make sure words consisting of many labels stay together
                     ui.style_mut().spacing.item_spacing.x = 0.;
                     ui.style_mut().spacing.window_padding.x = 0.;
 
                     for row in 0..rows {
-                        ui.horizontal(|ui| {
+                        ui.with_layout(row_layout(*text_direction), |ui| {
                             for word_index in 0..words_per_row {
                                 let current_index = get_current_word_index(
                                     row,
@@ -307,17 +1232,25 @@ fn draw_ui(
                                     word_list_index.current_index,
                                     words_per_row,
                                 );
+                                if current_index >= word_list.list.len() {
+                                    break;
+                                }
                                 let current_word = &word_list.list[current_index];
-                                let previous_input =
-                                    get_previous_input(&player_word_list.list, current_index);
+                                let typed = typed_input(
+                                    &player_word_list.list,
+                                    &input_text.text,
+                                    word_list_index.current_index,
+                                    current_index,
+                                );
 
                                 add_word_to_ui(
                                     ui,
                                     word_list_index.current_index,
                                     current_index,
-                                    &input_text.text,
-                                    &previous_input,
+                                    &typed,
                                     &current_word,
+                                    *text_direction,
+                                    pace_caret.letter_in(current_index),
                                 );
 
                                 if word_index < words_per_row - 1 {
@@ -330,7 +1263,7 @@ fn draw_ui(
                 });
 
                 // This window is visible window that shows the player the words they need to type
-                WindowForLabels::new(window.width(), 0., end_point.rect.top()).show(
+                WindowForLabels::new(window.width(), 0., end_point.rect.top()).window().show(
                     ui.ctx(),
                     |ui| {
                         // To make sure words consisting of many labels stay together
@@ -342,7 +1275,8 @@ fn draw_ui(
 
                             ui.add_space(VERT_SPACE_BETWEEN_LABELS);
 
-                            ui.horizontal(|ui| {
+                            // Right to left rows start from the right, which mirrors the centering
+                            ui.with_layout(row_layout(*text_direction), |ui| {
                                 ui.add_space(unused_width / 4.);
 
                                 for word_index in 0..words_per_row {
@@ -352,17 +1286,25 @@ fn draw_ui(
                                         word_list_index.current_index,
                                         words_per_row,
                                     );
+                                    if current_index >= word_list.list.len() {
+                                        break;
+                                    }
                                     let current_word = &word_list.list[current_index];
-                                    let previous_input =
-                                        get_previous_input(&player_word_list.list, current_index);
+                                    let typed = typed_input(
+                                        &player_word_list.list,
+                                        &input_text.text,
+                                        word_list_index.current_index,
+                                        current_index,
+                                    );
 
                                     add_word_to_ui(
                                         ui,
                                         word_list_index.current_index,
                                         current_index,
-                                        &input_text.text,
-                                        &previous_input,
+                                        &typed,
                                         &current_word,
+                                        *text_direction,
+                                        pace_caret.letter_in(current_index),
                                     );
 
                                     if word_index < words_per_row - 1 {
@@ -380,45 +1322,147 @@ fn draw_ui(
 
                 //Clear the input field for the next round of typing
                 if move_index_by == 1 {
-                    word_list_index.current_index += 1;
+                    // The word as it was left, so playing the log back moves on with exactly it
                     player_word_list
-                        .list
-                        .push(input_text.text.trim().to_string());
+                        .record_keystroke(word_list_index.current_index, &input_text.text);
+                    word_list_index.current_index += 1;
+                    player_word_list.push_word(input_text.text.trim().to_string());
                     input_text.text = "".to_string();
                 } else if move_index_by == -1 {
                     word_list_index.current_index -= 1;
-                    input_text.text = player_word_list.list[word_list_index.current_index]
-                        .trim()
-                        .to_string();
-                    player_word_list.list.pop();
+                    if let Some(previous_word) = player_word_list.pop_word() {
+                        input_text.text = previous_word.trim().to_string();
+                    }
+                }
+
+                // Every change to the input is kept for the replay of this run
+                if (app_state.current() == &AppState::Playing || test_started)
+                    && (input_text.text != previous_text || move_index_by != 0)
+                {
+                    player_word_list
+                        .record_keystroke(word_list_index.current_index, &input_text.text);
                 }
             });
         });
 }
 
-fn create_new_word_list(commands: &mut Commands) {
-    let all_words = AllWords::new();
+fn create_new_word_list(
+    commands: &mut Commands,
+    game_mode: &GameMode,
+    word_pack: Option<&WordPack>,
+    book_state: &BookState,
+    code_state: &CodeState,
+    review_queue: &ReviewQueue,
+    config: &UserConfig,
+) {
+    let seed = rand::random();
+    let mut word_list = WordList {
+        list: generate_word_list(
+            game_mode,
+            word_pack,
+            book_state,
+            code_state,
+            review_queue,
+            config,
+            &mut StdRng::seed_from_u64(seed),
+        ),
+        seed,
+        tag: None,
+        pack: None,
+    };
+
+    // Only word packs can be written right to left
+    let mut text_direction = match (game_mode, word_pack) {
+        (GameMode::Words | GameMode::Review, Some(word_pack)) => word_pack.direction,
+        _ => TextDirection::Ltr,
+    };
+
+    // A ghost can only be raced on the words it typed, laid out the way they were
+    let mut ghost = Ghost::default();
+    if let PaceTarget::Ghost(path) = &config.pace {
+        match Replay::load(path) {
+            Ok(replay) => {
+                word_list = WordList {
+                    list: replay.words.clone(),
+                    seed: replay.seed,
+                    tag: Some("ghost".to_string()),
+                    pack: None,
+                };
+                text_direction = replay.settings.direction;
+                ghost.replay = Some(replay);
+            }
+            Err(error) => ghost.error = Some(error),
+        }
+    }
 
-    commands.insert_resource(AllWords::new());
-    commands.insert_resource(WordList::new(all_words.all_words.clone()));
+    commands.insert_resource(word_list);
+    commands.insert_resource(ghost);
+    commands.insert_resource(text_direction);
     commands.insert_resource(PlayerWordList::new());
     commands.insert_resource(WordListIndex { current_index: 0 });
 }
 
+fn generate_word_list(
+    game_mode: &GameMode,
+    word_pack: Option<&WordPack>,
+    book_state: &BookState,
+    code_state: &CodeState,
+    review_queue: &ReviewQueue,
+    config: &UserConfig,
+    rng: &mut StdRng,
+) -> Vec<String> {
+    match game_mode {
+        GameMode::Words => match word_pack {
+            // Without review words a proctored test only depends on the seed, so it can be checked
+            Some(word_pack) if config.locked => random_pack_words(word_pack, config, &[], rng),
+            Some(word_pack) => random_pack_words(
+                word_pack,
+                config,
+                &review_queue.due_words(&word_pack.id),
+                rng,
+            ),
+            None => Vec::new(),
+        },
+        GameMode::Book => book_state.current_page_words(),
+        GameMode::Code => code_state.random_word_list(rng),
+        GameMode::Numbers => get_number_drill_list(rng),
+        GameMode::Symbols => get_symbol_drill_list(rng),
+        GameMode::Review => {
+            let mut words = match word_pack {
+                Some(word_pack) => review_queue.due_words(&word_pack.id),
+                None => Vec::new(),
+            };
+            words.truncate(WORD_COUNT_PER_GAME);
+            words
+        }
+    }
+}
+
+fn random_pack_words(
+    word_pack: &WordPack,
+    config: &UserConfig,
+    due_words: &[String],
+    rng: &mut StdRng,
+) -> Vec<String> {
+    let pack_words = pipeline_pack_words(&config.transforms, &word_pack.words);
+    let words = get_pack_word_list(&pack_words, &config.word_filters, due_words, rng);
+    let words = apply_pipeline(&config.transforms, words, rng);
+
+    config.modifiers.apply(words, rng)
+}
+
 fn get_current_word_index(
     row_index: usize,
     word_index: usize,
     current_index: usize,
     words_per_row: usize,
 ) -> usize {
-    let current_row = (current_index as f32 / words_per_row as f32).floor();
-    let word_list_index = (row_index * words_per_row)
-        + word_index
-        + (max((current_row as i8) - 1, 0) * words_per_row as i8) as usize;
-    word_list_index as usize
+    // The rows shown start a row above the current one; a long zen run gets to thousands of words
+    let first_row_offset = (current_index / words_per_row).saturating_sub(1) * words_per_row;
+    (row_index * words_per_row) + word_index + first_row_offset
 }
 
-fn get_previous_input(player_word_list: &Vec<String>, index: usize) -> String {
+fn get_previous_input(player_word_list: &[String], index: usize) -> String {
     if index < player_word_list.len() {
         player_word_list[index].to_string()
     } else {
@@ -426,78 +1470,124 @@ fn get_previous_input(player_word_list: &Vec<String>, index: usize) -> String {
     }
 }
 
+/// The input field for the word being typed, what was submitted for the words before it
+fn typed_input(
+    player_word_list: &[String],
+    input: &str,
+    player_index: usize,
+    index: usize,
+) -> String {
+    if index == player_index {
+        input.to_string()
+    } else {
+        get_previous_input(player_word_list, index)
+    }
+}
+
 fn add_word_to_ui(
     ui: &mut Ui,
     player_index: usize,
     word_index: usize,
-    current_input: &String,
-    previous_input: &String,
-    current_word: &String,
+    typed: &str,
+    current_word: &str,
+    text_direction: TextDirection,
+    pace_letter: Option<usize>,
 ) {
     // If this isn't the current word being typed
     if player_index != word_index {
-        if word_index > player_index {
-            ui.add(Label::new(
-                RichText::new(&current_word[..]).color(Color32::WHITE),
-            ));
-        } else if previous_input.trim() == current_word.trim() {
-            ui.add(Label::new(
-                RichText::new(&current_word[..]).color(Color32::GREEN),
-            ));
+        let color = if word_index > player_index {
+            Color32::WHITE
+        } else if typed.trim() == current_word.trim() {
+            Color32::GREEN
         } else {
-            ui.add(Label::new(
-                RichText::new(&current_word[..]).color(Color32::RED),
-            ));
+            Color32::RED
+        };
+
+        match pace_letter {
+            // The pace caret needs the word split into letters to sit on one of them
+            Some(pace_letter) => {
+                for (letter, text) in shaped_letters(current_word).iter().enumerate() {
+                    if letter == pace_letter {
+                        create_label(ui, text, color, colors::PACE_CARET_COLOR);
+                    } else {
+                        create_label(ui, text, color, Color32::TRANSPARENT);
+                    }
+                }
+            }
+            None => {
+                let shown_word = visual_order(current_word, text_direction);
+                ui.add(Label::new(RichText::new(shown_word).color(color)));
+            }
         }
     } else {
-        // Check how far into the word we are and if they match
-        let length_typed = current_input.len();
-        let word_length = current_word.len();
-
-        for letter in 0..word_length {
-            if letter < length_typed {
-                if one_char(&current_word, letter) == one_char(&current_input, letter) {
-                    create_label(ui, one_char(&current_word, letter), Color32::WHITE);
+        // Check how far into the word we are and if they match; letters are compared in
+        // logical order and a right to left row lays the labels out from the right
+        let typed_letters = graphemes(typed);
+        let word_letters = graphemes(current_word);
+        let shown_letters = shaped_letters(current_word);
+
+        for letter in 0..word_letters.len() {
+            let color =
+                if letter < typed_letters.len() && word_letters[letter] != typed_letters[letter] {
+                    Color32::RED
                 } else {
-                    create_label(ui, one_char(&current_word, letter), Color32::RED);
-                }
+                    Color32::WHITE
+                };
+            let background = if pace_letter == Some(letter) {
+                colors::PACE_CARET_COLOR
             } else {
-                create_label(ui, one_char(&current_word, letter), Color32::WHITE);
-            }
+                Color32::BLACK
+            };
+
+            create_label(ui, &shown_letters[letter], color, background);
         }
     }
 }
 
-fn one_char(word: &str, letter: usize) -> &str {
-    &word[letter..letter + 1]
+fn row_layout(text_direction: TextDirection) -> Layout {
+    match text_direction {
+        TextDirection::Ltr => Layout::left_to_right(),
+        TextDirection::Rtl => Layout::right_to_left(),
+    }
+    .with_cross_align(Align::Center)
 }
 
-fn create_label(ui: &mut Ui, letter: &str, color: Color32) {
+fn create_label(ui: &mut Ui, letter: &str, color: Color32, background: Color32) {
     ui.add(Label::new(
         RichText::new(letter)
             .color(color)
-            .background_color(Color32::BLACK),
+            .background_color(background),
     ));
 }
 
-struct GameStats {
+pub struct GameStats {
     cpm: u16,
     correct_cpm: u16,
     wpm: u16,
+    previous_best_wpm: Option<u16>,
+    failure: Option<Failure>,
+    ghost_finish: Option<GhostFinish>,
+    key_stats: KeyStats,
+    all_time_key_stats: KeyStats,
 }
 
-fn get_game_stats(word_list: &Vec<String>, player_word_list: &Vec<String>) -> GameStats {
-    let mut cpm: u16 = 0;
-    let mut correct_cpm: u16 = 0;
-    let mut wpm: u16 = 0;
+fn get_game_stats(
+    word_list: &[String],
+    player_word_list: &[String],
+    seconds_played: u32,
+) -> GameStats {
+    // Counted in u32, a long zen run can type more characters than a u16 holds
+    let mut cpm: u32 = 0;
+    let mut correct_cpm: u32 = 0;
+    let mut wpm: u32 = 0;
 
     for i in 0..player_word_list.len() {
-        let word_length = word_list[i].len() as u16;
-        let player_word_length = player_word_list[i].len() as u16;
+        let word_length = grapheme_count(&word_list[i]) as u32;
+        let player_word_length = grapheme_count(&player_word_list[i]) as u32;
 
         // If the player typed more chararacters its already wrong
         if player_word_length > word_length {
-            cpm += player_word_length as u16;
+            cpm += player_word_length;
             continue;
         }
 
@@ -509,11 +1599,11 @@ fn get_game_stats(word_list: &Vec<String>, player_word_list: &Vec<String>) -> Ga
         }
         // If the strings are different, only count the correct characters
         else {
-            let mut player_chars = player_word_list[i].chars();
-            let mut word_chars = word_list[i].chars();
+            let mut player_letters = graphemes(&player_word_list[i]).into_iter();
+            let mut word_letters = graphemes(&word_list[i]).into_iter();
 
             for _ in 0..player_word_length {
-                if player_chars.next() == word_chars.next() {
+                if player_letters.next() == word_letters.next() {
                     correct_cpm += 1;
                 }
                 cpm += 1;
@@ -522,8 +1612,46 @@ fn get_game_stats(word_list: &Vec<String>, player_word_list: &Vec<String>) -> Ga
     }
 
     GameStats {
-        cpm,
-        correct_cpm,
-        wpm,
+        cpm: per_minute(cpm, seconds_played),
+        correct_cpm: per_minute(correct_cpm, seconds_played),
+        wpm: per_minute(wpm, seconds_played),
+        previous_best_wpm: None,
+        failure: None,
+        ghost_finish: None,
+        key_stats: KeyStats::new(),
+        all_time_key_stats: KeyStats::new(),
+    }
+}
+
+impl GameStats {
+    fn accuracy(&self) -> u16 {
+        if self.cpm == 0 {
+            return 100;
+        }
+        (self.correct_cpm as u32 * 100 / self.cpm as u32) as u16
+    }
+}
+
+fn per_minute(count: u32, seconds_played: u32) -> u16 {
+    (count as u64 * 60 / seconds_played.max(1) as u64).min(u16::MAX as u64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shown_rows_start_one_row_above_the_current_one() {
+        assert_eq!(get_current_word_index(0, 2, 3, 6), 2);
+        assert_eq!(get_current_word_index(1, 0, 8, 6), 6);
+        assert_eq!(get_current_word_index(0, 0, 14, 6), 6);
+    }
+
+    #[test]
+    fn word_indexes_past_a_few_hundred_words_do_not_overflow() {
+        // Row 41 is the current one, so the first row shown is row 40
+        assert_eq!(get_current_word_index(0, 2, 250, 6), 242);
+        assert_eq!(get_current_word_index(2, 5, 250, 6), 257);
+        assert_eq!(get_current_word_index(0, 0, 100_000, 7), 99_988);
     }
 }
@@ -0,0 +1,86 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const SENTENCE_END_CHANCE: f64 = 0.12;
+const COMMA_CHANCE: f64 = 0.1;
+const QUOTE_CHANCE: f64 = 0.06;
+const PARENTHESES_CHANCE: f64 = 0.05;
+const NUMBER_CHANCE: f64 = 0.1;
+const MAX_NUMBER: u32 = 9999;
+
+/// Optional changes to the generated words, applied on top of random word tests
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Modifiers {
+    pub punctuation: bool,
+    pub quotes: bool,
+    pub parentheses: bool,
+    pub numbers: bool,
+}
+
+impl Modifiers {
+    /// Names of the active modifiers, stored with a result so runs are only compared to similar ones
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+
+        if self.punctuation {
+            tags.push("punctuation".to_string());
+        }
+        if self.quotes {
+            tags.push("quotes".to_string());
+        }
+        if self.parentheses {
+            tags.push("parentheses".to_string());
+        }
+        if self.numbers {
+            tags.push("numbers".to_string());
+        }
+        tags
+    }
+
+    pub fn apply(&self, words: Vec<String>, rng: &mut StdRng) -> Vec<String> {
+        let mut sentence_start = true;
+
+        words
+            .into_iter()
+            .map(|word| {
+                let mut word = word;
+
+                if self.numbers && rng.gen_bool(NUMBER_CHANCE) {
+                    word = rng.gen_range(0..=MAX_NUMBER).to_string();
+                }
+
+                if self.punctuation && sentence_start {
+                    word = capitalize(&word);
+                }
+                sentence_start = false;
+
+                if self.quotes && rng.gen_bool(QUOTE_CHANCE) {
+                    word = format!("\"{}\"", word);
+                } else if self.parentheses && rng.gen_bool(PARENTHESES_CHANCE) {
+                    word = format!("({})", word);
+                }
+
+                if self.punctuation {
+                    if rng.gen_bool(SENTENCE_END_CHANCE) {
+                        word.push('.');
+                        sentence_start = true;
+                    } else if rng.gen_bool(COMMA_CHANCE) {
+                        word.push(',');
+                    }
+                }
+                word
+            })
+            .collect()
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
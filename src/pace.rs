@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::UserConfig;
+use crate::game_mode::GameMode;
+use crate::history::{History, TestResult};
+use crate::replay::Replay;
+use crate::run_mode::RunMode;
+use crate::text::grapheme_count;
+use crate::word_generator::{PlayerWordList, WordList};
+use crate::word_packs::WordPacks;
+use crate::AppState;
+
+pub const DEFAULT_PACE_WPM: u16 = 40;
+pub const MIN_PACE_WPM: u16 = 10;
+pub const MAX_PACE_WPM: u16 = 200;
+
+/// What the pace caret follows
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Eq, PartialEq)]
+pub enum PaceTarget {
+    #[default]
+    Off,
+    Wpm(u16),
+    /// The best WPM of earlier runs typed under the same conditions
+    PersonalBest,
+    /// The keystrokes of a replay file, typed on that replay's words
+    Ghost(String),
+}
+
+impl PaceTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaceTarget::Off => "Off",
+            PaceTarget::Wpm(_) => "Target WPM",
+            PaceTarget::PersonalBest => "Personal Best",
+            PaceTarget::Ghost(_) => "Ghost",
+        }
+    }
+}
+
+pub const ALL_PACE_TARGETS: [PaceTarget; 4] = [
+    PaceTarget::Off,
+    PaceTarget::Wpm(DEFAULT_PACE_WPM),
+    PaceTarget::PersonalBest,
+    PaceTarget::Ghost(String::new()),
+];
+
+/// The replay being raced, loaded together with the word list it was typed on
+#[derive(Default)]
+pub struct Ghost {
+    pub replay: Option<Replay>,
+    pub error: Option<String>,
+}
+
+/// How a finished test compares to the ghost it raced
+#[derive(Clone)]
+pub struct GhostFinish {
+    pub wpm: u16,
+    /// Words the player finished ahead of the ghost, negative when behind
+    pub words_ahead: i64,
+}
+
+/// Where a second caret is in the word list, so the player can see if they are ahead or behind
+#[derive(Default)]
+pub struct PaceCaret {
+    /// What the caret follows, None when there is no caret
+    pub label: Option<String>,
+    /// Why the ghost that was picked can't be raced
+    pub error: Option<String>,
+    pub word_index: usize,
+    pub letter_index: usize,
+    /// The best WPM to pace against, looked up once per test since it goes over the whole history
+    personal_best: Option<u16>,
+}
+
+impl PaceCaret {
+    /// The letter of this word the caret is on, if it is on this word at all
+    pub fn letter_in(&self, word_index: usize) -> Option<usize> {
+        match self.label {
+            Some(_) if self.word_index == word_index => Some(self.letter_index),
+            _ => None,
+        }
+    }
+
+    /// How many words the player is ahead of the caret, negative when behind
+    pub fn words_ahead(&self, player_word_index: usize) -> i64 {
+        player_word_index as i64 - self.word_index as i64
+    }
+}
+
+/// Moves the caret along at the target speed; WPM here means whole words, like in the results
+#[allow(clippy::too_many_arguments)]
+pub fn update_pace_caret(
+    app_state: Res<State<AppState>>,
+    config: Res<UserConfig>,
+    game_mode: Res<GameMode>,
+    run_mode: Res<RunMode>,
+    history: Res<History>,
+    word_packs: Res<WordPacks>,
+    ghost: Res<Ghost>,
+    word_list: Res<WordList>,
+    player_word_list: Res<PlayerWordList>,
+    mut pace_caret: ResMut<PaceCaret>,
+) {
+    // A new word list means a new test; this runs before the state check, as the list is
+    // usually made while the state is still changing
+    if word_list.is_changed() || game_mode.is_changed() || run_mode.is_changed() {
+        pace_caret.personal_best = match config.pace {
+            PaceTarget::PersonalBest => {
+                let mut result =
+                    TestResult::new(game_mode.name(), run_mode.duration().unwrap_or(0), 0, 0, 0);
+                result.tags =
+                    config.result_tags(&game_mode, &run_mode, word_packs.get(&config.word_pack));
+                history.best_wpm(&result)
+            }
+            _ => None,
+        };
+    }
+
+    match app_state.current() {
+        AppState::ReadyToPlay | AppState::Playing | AppState::Paused => {}
+        _ => {
+            pace_caret.label = None;
+            pace_caret.error = None;
+            return;
+        }
+    }
+
+    let elapsed_seconds = player_word_list.elapsed_seconds();
+    pace_caret.error = ghost.error.clone();
+
+    let wpm = match &config.pace {
+        PaceTarget::Off => None,
+        PaceTarget::Wpm(wpm) => Some(*wpm),
+        PaceTarget::PersonalBest => pace_caret.personal_best,
+        // The ghost goes exactly where its keystrokes went
+        PaceTarget::Ghost(_) => {
+            pace_caret.label = ghost
+                .replay
+                .as_ref()
+                .map(|replay| format!("Ghost {} WPM", replay.wpm));
+
+            if let Some(replay) = &ghost.replay {
+                let (word_index, letter_index) = replay.position_at(elapsed_seconds);
+                pace_caret.word_index = word_index;
+                pace_caret.letter_index = letter_index;
+            }
+            return;
+        }
+    };
+
+    pace_caret.label = wpm.map(|wpm| format!("Pace {} WPM", wpm));
+
+    let wpm = match wpm {
+        Some(wpm) => wpm,
+        None => return,
+    };
+
+    let words = wpm as f32 * elapsed_seconds / 60.;
+    let word_index = words.floor() as usize;
+    let word_length = word_list
+        .list
+        .get(word_index)
+        .map(|word| grapheme_count(word))
+        .unwrap_or(0);
+
+    pace_caret.word_index = word_index;
+    pace_caret.letter_index = (words.fract() * word_length as f32) as usize;
+}
@@ -0,0 +1,711 @@
+use hmac::{Hmac, Mac};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use sha2::Sha256;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::UserConfig;
+use crate::replay::{typed_words_at, Keystroke};
+use crate::theme::ThemeKind;
+use crate::word_generator::{PlayerWordList, WordList};
+use crate::word_packs::{WordPacks, DEFAULT_PACK};
+use crate::{get_game_stats, random_pack_words, GameStats};
+
+/// Left next to the app by whoever runs the test; the app is proctored while it is there
+const PROCTOR_FILE: &str = "proctor.json";
+/// The signing key is passed to the app when it is launched instead of being left in a file
+/// the candidate can open
+const PROCTOR_KEY_VARIABLE: &str = "TYPING_TESTER_PROCTOR_KEY";
+/// Bumped whenever the signed result changes in a way older verifiers can't check
+const PROCTORED_RESULT_VERSION: u32 = 2;
+/// Key presses are timed from the first one, the test clock from the start of its first second
+const CLOCK_TOLERANCE_SECONDS: f32 = 1.;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ProctorConfig {
+    /// Signs the results; taken from the environment at launch, never from the file
+    #[serde(skip)]
+    pub key: String,
+    pub candidate: String,
+    pub word_pack: String,
+    pub results_directory: String,
+}
+
+impl Default for ProctorConfig {
+    fn default() -> Self {
+        Self {
+            key: String::new(),
+            candidate: String::new(),
+            word_pack: DEFAULT_PACK.to_string(),
+            results_directory: "proctored".to_string(),
+        }
+    }
+}
+
+impl ProctorConfig {
+    fn load() -> Result<Option<Self>, String> {
+        let json = match fs::read_to_string(PROCTOR_FILE) {
+            Ok(json) => json,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("{} could not be read: {}", PROCTOR_FILE, error)),
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&json)
+            .map_err(|error| format!("{} is not valid: {}", PROCTOR_FILE, error))?;
+        if value.get("key").is_some() {
+            return Err(format!(
+                "{} must not hold the key, set {} when starting the app instead",
+                PROCTOR_FILE, PROCTOR_KEY_VARIABLE
+            ));
+        }
+        let mut config: ProctorConfig = serde_json::from_value(value)
+            .map_err(|error| format!("{} is not valid: {}", PROCTOR_FILE, error))?;
+
+        config.key = take_key().ok_or_else(|| {
+            format!(
+                "{} is set up, but the app wasn't started with {} to sign results with",
+                PROCTOR_FILE, PROCTOR_KEY_VARIABLE
+            )
+        })?;
+        Ok(Some(config))
+    }
+
+    /// A timed random word test on the pack, with every other setting at its default
+    pub fn user_config(&self, theme: ThemeKind) -> UserConfig {
+        UserConfig {
+            word_pack: self.word_pack.clone(),
+            theme,
+            locked: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Removed from the environment once read, so programs started by the app don't inherit it
+fn take_key() -> Option<String> {
+    let key = std::env::var(PROCTOR_KEY_VARIABLE).ok()?;
+    std::env::remove_var(PROCTOR_KEY_VARIABLE);
+
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// Whether this is a proctored test, and what happened to the last signed result
+#[derive(Default)]
+pub struct Proctor {
+    pub config: Option<ProctorConfig>,
+    pub error: Option<String>,
+    /// The path of the last signed result, or why it couldn't be saved
+    pub saved: Option<Result<String, String>>,
+    /// Tells the attempts of this launch apart from those of another one
+    session: String,
+    /// Every test started since the app was launched, finished or not
+    attempts: u32,
+    /// The last attempt started and wasn't signed yet
+    attempt_open: bool,
+}
+
+impl Proctor {
+    pub fn load() -> Self {
+        let session = format!("{:016x}", rand::random::<u64>());
+        match ProctorConfig::load() {
+            Ok(config) => Self {
+                config,
+                session,
+                ..Default::default()
+            },
+            Err(error) => Self {
+                error: Some(error),
+                session,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Called when a new test starts; an attempt left without signing it is signed as aborted
+    /// first, so trying again always leaves a trace
+    pub fn start_attempt(
+        &mut self,
+        word_list: &WordList,
+        player_word_list: &PlayerWordList,
+        seconds_played: u32,
+    ) {
+        if self.config.is_none() {
+            return;
+        }
+        self.abort_attempt(word_list, player_word_list, seconds_played);
+        self.attempts += 1;
+        self.attempt_open = true;
+    }
+
+    /// Signs the attempt under way as aborted, such as when going back to the menu
+    pub fn abort_attempt(
+        &mut self,
+        word_list: &WordList,
+        player_word_list: &PlayerWordList,
+        seconds_played: u32,
+    ) {
+        if self.attempt_open {
+            let seconds_played = seconds_played.max(1);
+            let game_stats =
+                get_game_stats(&word_list.list, &player_word_list.list, seconds_played);
+            self.sign_attempt(
+                word_list,
+                &player_word_list.keystrokes,
+                seconds_played,
+                &game_stats,
+                true,
+            );
+        }
+    }
+
+    /// Signs the attempt that just ended and keeps where it was saved to show it
+    pub fn sign_attempt(
+        &mut self,
+        word_list: &WordList,
+        keystrokes: &[Keystroke],
+        seconds_played: u32,
+        game_stats: &GameStats,
+        aborted: bool,
+    ) {
+        if let Some(config) = &self.config {
+            let attempt = Attempt {
+                session: self.session.clone(),
+                number: self.attempts,
+                aborted,
+            };
+            self.saved = Some(save_signed_result(
+                config,
+                attempt,
+                word_list,
+                keystrokes,
+                seconds_played,
+                game_stats,
+            ));
+            self.attempt_open = false;
+        }
+    }
+}
+
+/// Which of the tests started in one launch of the app a result is
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct Attempt {
+    session: String,
+    /// Counted from 1
+    number: u32,
+    /// Left before the test ended, by going back to the menu or starting over
+    aborted: bool,
+}
+
+/// Everything needed to check a proctored test without trusting the stats in it
+#[derive(Serialize, Deserialize)]
+struct ProctoredResult {
+    version: u32,
+    candidate: String,
+    timestamp: u64,
+    word_pack: String,
+    seed: u64,
+    /// Missing from results signed before attempts were counted
+    #[serde(default)]
+    attempt: Attempt,
+    seconds_played: u32,
+    words: Vec<String>,
+    keystrokes: Vec<Keystroke>,
+    stats: RecordedStats,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedStats {
+    cpm: u16,
+    correct_cpm: u16,
+    wpm: u16,
+    accuracy: u16,
+}
+
+impl RecordedStats {
+    fn new(game_stats: &GameStats) -> Self {
+        Self {
+            cpm: game_stats.cpm,
+            correct_cpm: game_stats.correct_cpm,
+            wpm: game_stats.wpm,
+            accuracy: game_stats.accuracy(),
+        }
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{} WPM, {} CPM, {} correct CPM, {}% accuracy",
+            self.wpm, self.cpm, self.correct_cpm, self.accuracy
+        )
+    }
+}
+
+/// The signature covers the exact bytes of the result, so the file can't be reformatted
+#[derive(Serialize, Deserialize)]
+struct SignedResult {
+    result: Box<RawValue>,
+    /// HMAC-SHA256 of the result with the proctor key, in hex
+    signature: String,
+}
+
+fn signer(key: &str) -> HmacSha256 {
+    // HMAC takes keys of any length
+    HmacSha256::new_from_slice(key.as_bytes()).unwrap()
+}
+
+/// The result with its signature, as the text of a signed result file
+fn sign(result: &ProctoredResult, key: &str) -> Result<String, String> {
+    let json = serde_json::to_string(result).map_err(|error| error.to_string())?;
+    let mut mac = signer(key);
+    mac.update(json.as_bytes());
+
+    let signed = SignedResult {
+        result: RawValue::from_string(json).map_err(|error| error.to_string())?,
+        signature: hex::encode(mac.finalize().into_bytes()),
+    };
+    serde_json::to_string(&signed).map_err(|error| error.to_string())
+}
+
+/// Writes the test to the results directory, signed with the proctor key
+fn save_signed_result(
+    config: &ProctorConfig,
+    attempt: Attempt,
+    word_list: &WordList,
+    keystrokes: &[Keystroke],
+    seconds_played: u32,
+    game_stats: &GameStats,
+) -> Result<String, String> {
+    let result = ProctoredResult {
+        version: PROCTORED_RESULT_VERSION,
+        candidate: config.candidate.clone(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        word_pack: config.word_pack.clone(),
+        seed: word_list.seed,
+        attempt,
+        seconds_played,
+        words: word_list.list.clone(),
+        keystrokes: keystrokes.to_vec(),
+        stats: RecordedStats::new(game_stats),
+    };
+
+    let signed = sign(&result, &config.key)?;
+
+    fs::create_dir_all(&config.results_directory).map_err(|error| {
+        format!(
+            "{} could not be created: {}",
+            config.results_directory, error
+        )
+    })?;
+
+    let name = if config.candidate.trim().is_empty() {
+        "candidate".to_string()
+    } else {
+        config
+            .candidate
+            .trim()
+            .replace(|c: char| !c.is_alphanumeric(), "_")
+    };
+    let path = Path::new(&config.results_directory)
+        .join(format!(
+            "{}-{}-attempt-{}.json",
+            name, result.attempt.session, result.attempt.number
+        ))
+        .to_string_lossy()
+        .to_string();
+
+    fs::write(&path, signed)
+        .map_err(|error| format!("The signed result could not be saved: {}", error))?;
+    Ok(path)
+}
+
+/// `verify FILE [--key KEY]`, the key defaults to the one in the environment; returns the
+/// exit code
+pub fn verify_command(args: &[String]) -> i32 {
+    let mut path = None;
+    let mut key = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => key = args.next().cloned(),
+            _ => path = Some(arg.clone()),
+        }
+    }
+
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: typing-tester verify FILE [--key KEY]");
+            return 2;
+        }
+    };
+    let key = match key.or_else(take_key) {
+        Some(key) => key,
+        None => {
+            eprintln!("No --key given and {} is not set", PROCTOR_KEY_VARIABLE);
+            return 2;
+        }
+    };
+
+    match verify(&path, &key) {
+        Ok(()) => {
+            println!("VERIFIED");
+            0
+        }
+        Err(error) => {
+            println!("NOT VERIFIED: {}", error);
+            1
+        }
+    }
+}
+
+/// Checks the signature, then plays the keystrokes back and recomputes the stats from them
+fn verify(path: &str, key: &str) -> Result<(), String> {
+    let json = fs::read_to_string(path)
+        .map_err(|error| format!("{} could not be read: {}", path, error))?;
+    let signed: SignedResult = serde_json::from_str(&json)
+        .map_err(|error| format!("{} is not a signed result: {}", path, error))?;
+
+    let signature =
+        hex::decode(&signed.signature).map_err(|_| "The signature is not valid hex".to_string())?;
+    let mut mac = signer(key);
+    mac.update(signed.result.get().as_bytes());
+    mac.verify_slice(&signature).map_err(|_| {
+        "The signature doesn't match, the file was changed or signed with another key".to_string()
+    })?;
+    println!("Signature: valid");
+
+    let result: ProctoredResult = serde_json::from_str(signed.result.get())
+        .map_err(|error| format!("The result is not valid: {}", error))?;
+    if result.version > PROCTORED_RESULT_VERSION {
+        return Err(format!(
+            "The result is version {}, this app can only check up to version {}",
+            result.version, PROCTORED_RESULT_VERSION
+        ));
+    }
+    println!("Candidate: {}", result.candidate);
+    println!(
+        "Test: {} pack, {} seconds, taken at {}",
+        result.word_pack, result.seconds_played, result.timestamp
+    );
+    println!("Attempts: {}", attempts_text(&result));
+
+    check_keystrokes(&result)?;
+
+    let (typed_words, _) = typed_words_at(&result.keystrokes, f32::MAX);
+    if typed_words.len() > result.words.len() {
+        return Err("The keystrokes type more words than the test had".to_string());
+    }
+    let replayed = RecordedStats::new(&get_game_stats(
+        &result.words,
+        &typed_words,
+        result.seconds_played.max(1),
+    ));
+    println!(
+        "Replayed {} keystrokes: {}",
+        result.keystrokes.len(),
+        replayed.text()
+    );
+
+    if (replayed.cpm, replayed.correct_cpm, replayed.wpm)
+        != (result.stats.cpm, result.stats.correct_cpm, result.stats.wpm)
+    {
+        return Err(format!(
+            "The result says {}, the keystrokes don't",
+            result.stats.text()
+        ));
+    }
+
+    check_words(&result)
+}
+
+/// How many tests the candidate started in the launch this one is from, up to and including it
+fn attempts_text(result: &ProctoredResult) -> String {
+    if result.version < 2 {
+        return "not counted by the app that signed this result".to_string();
+    }
+
+    format!(
+        "{} in session {}, this one {}",
+        result.attempt.number,
+        result.attempt.session,
+        if result.attempt.aborted {
+            "aborted"
+        } else {
+            "finished"
+        }
+    )
+}
+
+fn check_keystrokes(result: &ProctoredResult) -> Result<(), String> {
+    let mut previous_seconds = 0.;
+
+    for keystroke in &result.keystrokes {
+        if keystroke.seconds < previous_seconds {
+            return Err("The keystrokes are not in the order they were typed".to_string());
+        }
+        if keystroke.word > result.words.len() {
+            return Err("A keystroke is on a word the test didn't have".to_string());
+        }
+        previous_seconds = keystroke.seconds;
+    }
+
+    if previous_seconds > result.seconds_played as f32 + CLOCK_TOLERANCE_SECONDS {
+        return Err(format!(
+            "Keys were typed for {:.1} seconds in a {} second test",
+            previous_seconds, result.seconds_played
+        ));
+    }
+    Ok(())
+}
+
+/// The words have to be the ones the seed gives, so a test can't be swapped for an easier one
+fn check_words(result: &ProctoredResult) -> Result<(), String> {
+    let word_packs = WordPacks::load();
+    let word_pack = match word_packs
+        .packs
+        .iter()
+        .find(|word_pack| word_pack.id == result.word_pack)
+    {
+        Some(word_pack) => word_pack,
+        // Without the pack any words pass, so the result can't be called verified
+        None => {
+            return Err(format!(
+                "The words can't be checked, the {} pack isn't installed",
+                result.word_pack
+            ))
+        }
+    };
+
+    // The settings every proctored test runs with
+    let config = ProctorConfig::default().user_config(ThemeKind::default());
+    let words = random_pack_words(
+        word_pack,
+        &config,
+        &[],
+        &mut StdRng::seed_from_u64(result.seed),
+    );
+
+    if words != result.words {
+        return Err("The words are not the ones the seed gives".to_string());
+    }
+    println!("Words: match the seed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "a long random secret";
+
+    /// A signed result written to a temp file, removed when dropped
+    struct TestFile(tempfile::NamedTempFile);
+
+    impl TestFile {
+        fn new(contents: &str) -> Self {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            fs::write(file.path(), contents).unwrap();
+            Self(file)
+        }
+
+        fn verify(&self, key: &str) -> Result<(), String> {
+            verify(&self.0.path().to_string_lossy(), key)
+        }
+    }
+
+    /// The first words of a proctored test on the default pack, typed without a mistake
+    fn typed_result() -> ProctoredResult {
+        let word_packs = WordPacks::load();
+        let word_pack = word_packs
+            .packs
+            .iter()
+            .find(|word_pack| word_pack.id == DEFAULT_PACK)
+            .unwrap();
+        let seed = 42;
+        let words = random_pack_words(
+            word_pack,
+            &ProctorConfig::default().user_config(ThemeKind::default()),
+            &[],
+            &mut StdRng::seed_from_u64(seed),
+        );
+
+        let mut keystrokes = Vec::new();
+        let mut seconds = 0.;
+        for (index, word) in words.iter().take(5).enumerate() {
+            let mut input = String::new();
+            for letter in word.chars() {
+                input.push(letter);
+                seconds += 0.2;
+                keystrokes.push(Keystroke {
+                    seconds,
+                    word: index,
+                    input: input.clone(),
+                });
+            }
+            seconds += 0.2;
+            keystrokes.push(Keystroke {
+                seconds,
+                word: index + 1,
+                input: String::new(),
+            });
+        }
+
+        let seconds_played = 60;
+        let (typed_words, _) = typed_words_at(&keystrokes, f32::MAX);
+        let stats = RecordedStats::new(&get_game_stats(&words, &typed_words, seconds_played));
+
+        ProctoredResult {
+            version: PROCTORED_RESULT_VERSION,
+            candidate: "Jane Doe".to_string(),
+            timestamp: 0,
+            word_pack: DEFAULT_PACK.to_string(),
+            seed,
+            attempt: Attempt {
+                session: "0123456789abcdef".to_string(),
+                number: 1,
+                aborted: false,
+            },
+            seconds_played,
+            words,
+            keystrokes,
+            stats,
+        }
+    }
+
+    #[test]
+    fn a_typed_result_is_verified() {
+        let file = TestFile::new(&sign(&typed_result(), KEY).unwrap());
+        assert_eq!(file.verify(KEY), Ok(()));
+    }
+
+    #[test]
+    fn a_changed_file_or_another_key_fails_the_signature() {
+        let signed = sign(&typed_result(), KEY).unwrap();
+        let changed = TestFile::new(&signed.replace("Jane Doe", "John Doe"));
+        assert!(changed.verify(KEY).unwrap_err().contains("signature"));
+
+        let file = TestFile::new(&signed);
+        assert!(file
+            .verify("another key")
+            .unwrap_err()
+            .contains("signature"));
+    }
+
+    #[test]
+    fn reordered_keystrokes_are_not_verified() {
+        let mut result = typed_result();
+        result.keystrokes.swap(1, 2);
+
+        let file = TestFile::new(&sign(&result, KEY).unwrap());
+        assert!(file.verify(KEY).unwrap_err().contains("order"));
+    }
+
+    #[test]
+    fn stats_the_keystrokes_dont_give_are_not_verified() {
+        let mut result = typed_result();
+        result.stats.wpm += 10;
+
+        let file = TestFile::new(&sign(&result, KEY).unwrap());
+        assert!(file
+            .verify(KEY)
+            .unwrap_err()
+            .contains("the keystrokes don't"));
+    }
+
+    #[test]
+    fn words_that_cant_be_checked_are_not_verified() {
+        let mut result = typed_result();
+        result.word_pack = "not_installed".to_string();
+
+        let file = TestFile::new(&sign(&result, KEY).unwrap());
+        assert!(file.verify(KEY).unwrap_err().contains("isn't installed"));
+    }
+
+    #[test]
+    fn verify_tells_how_many_attempts_were_started() {
+        let mut result = typed_result();
+        assert_eq!(
+            attempts_text(&result),
+            "1 in session 0123456789abcdef, this one finished"
+        );
+
+        result.version = 1;
+        assert_eq!(
+            attempts_text(&result),
+            "not counted by the app that signed this result"
+        );
+    }
+
+    #[test]
+    fn starting_over_signs_the_attempt_that_was_left() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut proctor = Proctor {
+            config: Some(ProctorConfig {
+                key: KEY.to_string(),
+                candidate: "Jane Doe".to_string(),
+                results_directory: directory.path().to_string_lossy().to_string(),
+                ..Default::default()
+            }),
+            session: "0123456789abcdef".to_string(),
+            ..Default::default()
+        };
+
+        let typed = typed_result();
+        let word_list = WordList {
+            list: typed.words.clone(),
+            seed: typed.seed,
+            tag: None,
+            pack: None,
+        };
+        let mut player_word_list = PlayerWordList::new();
+        let (typed_words, _) = typed_words_at(&typed.keystrokes, f32::MAX);
+        player_word_list.list = typed_words;
+        player_word_list.keystrokes = typed.keystrokes.clone();
+
+        // Nothing was left to sign before the first attempt
+        proctor.start_attempt(&word_list, &player_word_list, 0);
+        assert!(proctor.saved.is_none());
+
+        proctor.start_attempt(&word_list, &player_word_list, 20);
+        let aborted = proctor.saved.clone().unwrap().unwrap();
+        assert!(aborted.ends_with("Jane_Doe-0123456789abcdef-attempt-1.json"));
+
+        let game_stats = get_game_stats(&word_list.list, &player_word_list.list, 60);
+        proctor.sign_attempt(
+            &word_list,
+            &player_word_list.keystrokes,
+            60,
+            &game_stats,
+            false,
+        );
+        let finished = proctor.saved.clone().unwrap().unwrap();
+        assert!(finished.ends_with("Jane_Doe-0123456789abcdef-attempt-2.json"));
+
+        // Going back to the menu after the test ended has nothing left to sign
+        proctor.abort_attempt(&word_list, &player_word_list, 60);
+        assert_eq!(proctor.saved, Some(Ok(finished.clone())));
+
+        for (path, aborted) in [(aborted, true), (finished, false)] {
+            assert_eq!(verify(&path, KEY), Ok(()));
+            let signed: SignedResult =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            let result: ProctoredResult = serde_json::from_str(signed.result.get()).unwrap();
+            assert_eq!(result.attempt.aborted, aborted);
+        }
+    }
+}
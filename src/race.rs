@@ -0,0 +1,487 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align, Color32, Label, Layout, RichText, Ui, Widget};
+use bevy_egui::EguiContext;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::UserConfig;
+use crate::game_mode::GameMode;
+use crate::inbox::Inbox;
+use crate::pace::Ghost;
+use crate::race_protocol::{
+    encode, ClientMessage, LobbyPlayer, Racer, ServerMessage, DEFAULT_RACE_PORT,
+};
+use crate::run_mode::RunMode;
+use crate::widgets::{InputField, StyledButton, StyledCentralPanel};
+use crate::word_filters::WordFilters;
+use crate::word_generator::{get_pack_word_list, PlayerWordList, WordList, WordListIndex};
+use crate::word_packs::WordPacks;
+use crate::{AppState, GameStats, GameTimer};
+
+/// Results of a race are tagged with this, and the word list of a race carries it
+pub const RACE_TAG: &str = "race";
+
+const CONNECT_TIMEOUT_SECONDS: u64 = 3;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RacePhase {
+    /// Waiting for everyone in the lobby to be ready
+    Lobby,
+    /// The words are there, typing starts at this instant
+    Countdown(Instant),
+    Racing,
+    /// Done with the race, the others may still be typing
+    WaitingForResults,
+}
+
+struct Connection {
+    stream: TcpStream,
+    messages: Inbox<ServerMessage>,
+}
+
+/// The app's side of a race on a race server
+pub struct RaceClient {
+    pub address_input: String,
+    pub name_input: String,
+    connection: Option<Connection>,
+    pub players: Vec<LobbyPlayer>,
+    pub ready: bool,
+    pub phase: RacePhase,
+    /// The live standings during a race, the final ranking after it
+    pub racers: Vec<Racer>,
+    pub error: Option<String>,
+    /// The word index the server last heard about
+    sent_progress: usize,
+}
+
+impl Default for RaceClient {
+    fn default() -> Self {
+        Self {
+            address_input: format!("127.0.0.1:{}", DEFAULT_RACE_PORT),
+            name_input: "Player".to_string(),
+            connection: None,
+            players: Vec::new(),
+            ready: false,
+            phase: RacePhase::Lobby,
+            racers: Vec::new(),
+            error: None,
+            sent_progress: 0,
+        }
+    }
+}
+
+impl RaceClient {
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    pub fn connect(&mut self) {
+        let address = self.address_input.trim();
+        // The port can be left out when the server runs on the default one
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{}:{}", address, DEFAULT_RACE_PORT)
+        };
+
+        match open_connection(&address) {
+            Ok(connection) => {
+                self.connection = Some(connection);
+                self.error = None;
+                self.send(ClientMessage::Join {
+                    name: self.name_input.trim().to_string(),
+                });
+            }
+            Err(error) => self.error = Some(error),
+        }
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            // Ends the reader thread as well
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+        self.players.clear();
+        self.ready = false;
+        self.phase = RacePhase::Lobby;
+    }
+
+    pub fn toggle_ready(&mut self) {
+        self.ready = !self.ready;
+        self.send(ClientMessage::Ready { ready: self.ready });
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        let sent = match &self.connection {
+            Some(connection) => (&connection.stream)
+                .write_all(encode(&message).as_bytes())
+                .is_ok(),
+            None => return,
+        };
+
+        if !sent {
+            self.disconnect();
+            self.error = Some("Lost the connection to the race server".to_string());
+        }
+    }
+
+    /// Everything the server sent since the last frame
+    fn receive(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        let mut closed = false;
+
+        if let Some(connection) = &self.connection {
+            loop {
+                match connection.messages.try_take() {
+                    Ok(message) => messages.push(message),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        closed = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if closed {
+            self.disconnect();
+            self.error = Some("The race server closed the connection".to_string());
+        }
+        messages
+    }
+}
+
+fn open_connection(address: &str) -> Result<Connection, String> {
+    let socket_address = address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("{} is not a valid address", address))?;
+
+    let stream = TcpStream::connect_timeout(
+        &socket_address,
+        Duration::from_secs(CONNECT_TIMEOUT_SECONDS),
+    )
+    .map_err(|error| format!("Could not connect to {}: {}", address, error))?;
+    let _ = stream.set_nodelay(true);
+
+    let reader = stream
+        .try_clone()
+        .map_err(|error| format!("Could not connect to {}: {}", address, error))?;
+    let (sender, messages) = Inbox::channel();
+    thread::spawn(move || read_messages(reader, sender));
+
+    Ok(Connection { stream, messages })
+}
+
+// The channel closes when the socket does, which is how the app notices
+fn read_messages(stream: TcpStream, messages: Sender<ServerMessage>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if let Ok(message) = serde_json::from_str(&line) {
+            if messages.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Follows the server: sets up the shared words on start, runs the countdown and reports
+/// progress and the result of the test
+#[allow(clippy::too_many_arguments)]
+pub fn update_race(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    mut race: ResMut<RaceClient>,
+    word_packs: Res<WordPacks>,
+    mut game_mode: ResMut<GameMode>,
+    mut run_mode: ResMut<RunMode>,
+    mut input_text: ResMut<InputField>,
+    mut player_word_list: ResMut<PlayerWordList>,
+    word_list: Res<WordList>,
+    word_list_index: Res<WordListIndex>,
+    final_game_stats: Option<Res<GameStats>>,
+) {
+    for message in race.receive() {
+        match message {
+            ServerMessage::Lobby { players } => race.players = players,
+            ServerMessage::Start {
+                seed,
+                pack,
+                countdown_seconds,
+            } => {
+                let word_pack = match word_packs
+                    .packs
+                    .iter()
+                    .find(|word_pack| word_pack.id == pack)
+                {
+                    Some(word_pack) => word_pack,
+                    None => {
+                        race.error = Some(format!(
+                            "The race is on the {} word pack, which isn't installed",
+                            pack
+                        ));
+                        continue;
+                    }
+                };
+
+                // Without filters, modifiers or review words everyone gets the same words
+                let list = get_pack_word_list(
+                    &word_pack.words,
+                    &WordFilters::default(),
+                    &[],
+                    &mut StdRng::seed_from_u64(seed),
+                );
+                commands.insert_resource(WordList {
+                    list,
+                    seed,
+                    tag: Some(RACE_TAG.to_string()),
+                    pack: Some(word_pack.id.clone()),
+                });
+                commands.insert_resource(word_pack.direction);
+                commands.insert_resource(Ghost::default());
+                commands.insert_resource(PlayerWordList::new());
+                commands.insert_resource(WordListIndex { current_index: 0 });
+                commands.insert_resource(GameTimer::new());
+
+                // A race is a timed test, the server ends it when that time is up
+                *game_mode = GameMode::Words;
+                *run_mode = RunMode::Timed;
+
+                input_text.text = "".to_string();
+                input_text.enabled = false;
+                race.ready = false;
+                race.racers.clear();
+                race.sent_progress = 0;
+                race.error = None;
+                race.phase =
+                    RacePhase::Countdown(Instant::now() + Duration::from_secs(countdown_seconds));
+
+                if app_state.current() != &AppState::Race {
+                    let _ = app_state.set(AppState::Race);
+                }
+            }
+            ServerMessage::Standings { racers } => race.racers = racers,
+            ServerMessage::Results { racers } => {
+                race.racers = racers;
+                race.phase = RacePhase::Lobby;
+            }
+            ServerMessage::Error { message } => race.error = Some(message),
+        }
+    }
+
+    if let RacePhase::Countdown(starts_at) = race.phase {
+        if Instant::now() >= starts_at && app_state.set(AppState::Playing).is_ok() {
+            input_text.enabled = true;
+            player_word_list.start_timing();
+            race.phase = RacePhase::Racing;
+        }
+        return;
+    }
+
+    if race.phase != RacePhase::Racing {
+        return;
+    }
+
+    match app_state.current() {
+        // The race screen is still up the frame the countdown ends
+        AppState::Race | AppState::Playing | AppState::Paused => {
+            if word_list_index.current_index != race.sent_progress {
+                race.sent_progress = word_list_index.current_index;
+
+                let correct_words = player_word_list
+                    .list
+                    .iter()
+                    .zip(&word_list.list)
+                    .filter(|(typed, word)| typed == word)
+                    .count();
+                race.send(ClientMessage::Progress {
+                    words: player_word_list.list.len(),
+                    correct_words,
+                });
+            }
+        }
+        // The stats are in before the state changes, so these are the race's
+        AppState::GameOver => {
+            if let Some(game_stats) = final_game_stats {
+                race.send(ClientMessage::Finish {
+                    wpm: game_stats.wpm,
+                    accuracy: game_stats.accuracy(),
+                });
+            }
+            race.phase = RacePhase::WaitingForResults;
+        }
+        // Leaving the test gives up on the race, the server ranks whatever was typed
+        _ => race.phase = RacePhase::WaitingForResults,
+    }
+}
+
+pub fn draw_race_screen(
+    app_state: Res<State<AppState>>,
+    mut race: ResMut<RaceClient>,
+    config: Res<UserConfig>,
+    mut ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+) {
+    if app_state.current() != &AppState::Race {
+        return;
+    }
+
+    let window = windows.get_primary().unwrap();
+
+    StyledCentralPanel::new(window.width(), config.theme)
+        .central_panel()
+        .show(ctx.ctx_mut(), |ui| {
+            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                ui.add(Label::new(
+                    RichText::new("RACE").heading().color(Color32::GREEN),
+                ));
+                ui.add_space(40.);
+
+                if race.is_connected() {
+                    draw_lobby(ui, &mut race);
+                } else {
+                    draw_connect_form(ui, &mut race);
+                }
+
+                if let Some(error) = &race.error {
+                    ui.add_space(10.);
+                    ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+                }
+
+                if race.phase == RacePhase::Lobby && !race.racers.is_empty() {
+                    ui.add_space(30.);
+                    ui.heading("Last race");
+                    draw_ranking(ui, &race.racers);
+                }
+            });
+        });
+}
+
+fn draw_connect_form(ui: &mut Ui, race: &mut RaceClient) {
+    ui.add(Label::new(
+        RichText::new("Race others on your network, one of you runs race_server")
+            .color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    egui::Grid::new("race_connect").show(ui, |ui| {
+        ui.label("Server");
+        ui.text_edit_singleline(&mut race.address_input);
+        ui.end_row();
+
+        ui.label("Name");
+        ui.text_edit_singleline(&mut race.name_input);
+        ui.end_row();
+    });
+
+    if StyledButton::new("CONNECT").ui(ui).clicked() {
+        race.connect();
+    }
+}
+
+fn draw_lobby(ui: &mut Ui, race: &mut RaceClient) {
+    egui::Grid::new("race_lobby").striped(true).show(ui, |ui| {
+        for player in &race.players {
+            ui.label(player.name.as_str());
+            if player.ready {
+                ui.add(Label::new(RichText::new("READY").color(Color32::GREEN)));
+            } else {
+                ui.add(Label::new(RichText::new("waiting").color(Color32::YELLOW)));
+            }
+            ui.end_row();
+        }
+    });
+    ui.add_space(20.);
+
+    match race.phase {
+        RacePhase::Lobby => {
+            ui.add(Label::new(
+                RichText::new("The race starts once everyone is ready").color(Color32::YELLOW),
+            ));
+
+            let ready_text = if race.ready { "NOT READY" } else { "READY" };
+            if StyledButton::new(ready_text).ui(ui).clicked() {
+                race.toggle_ready();
+            }
+            if StyledButton::new("DISCONNECT").ui(ui).clicked() {
+                race.disconnect();
+            }
+        }
+        RacePhase::Countdown(starts_at) => {
+            let seconds_left = starts_at.saturating_duration_since(Instant::now());
+            ui.heading(format!("Starting in {}", seconds_left.as_secs() + 1));
+        }
+        RacePhase::Racing | RacePhase::WaitingForResults => {
+            ui.add(Label::new(
+                RichText::new("Waiting for the race to end").color(Color32::YELLOW),
+            ));
+        }
+    }
+}
+
+fn draw_ranking(ui: &mut Ui, racers: &[Racer]) {
+    egui::Grid::new("race_ranking")
+        .striped(true)
+        .show(ui, |ui| {
+            for (place, racer) in racers.iter().enumerate() {
+                ui.label(format!("{}.", place + 1));
+                ui.label(racer.name.as_str());
+                match &racer.result {
+                    Some(result) => {
+                        ui.label(format!("{} WPM", result.wpm));
+                        ui.label(format!("{}%", result.accuracy));
+                    }
+                    None => {
+                        ui.label(format!("{} words", racer.correct_words));
+                        ui.label("");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// The other racers next to the words, and the final ranking on the results screen
+pub fn draw_race_standings(
+    app_state: Res<State<AppState>>,
+    race: Res<RaceClient>,
+    word_list: Res<WordList>,
+    mut ctx: ResMut<EguiContext>,
+) {
+    match app_state.current() {
+        AppState::Playing | AppState::Paused | AppState::GameOver => {}
+        _ => return,
+    }
+    if !race.is_connected() || word_list.tag.as_deref() != Some(RACE_TAG) {
+        return;
+    }
+
+    let title = if race.phase == RacePhase::Lobby {
+        "Race results"
+    } else {
+        "Race"
+    };
+
+    egui::Window::new(title)
+        .id(egui::Id::new("race_standings"))
+        .anchor(egui::Align2::RIGHT_TOP, [-10., 10.])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            if race.phase == RacePhase::WaitingForResults {
+                ui.add(Label::new(
+                    RichText::new("Waiting for the others").color(Color32::YELLOW),
+                ));
+            }
+            draw_ranking(ui, &race.racers);
+        });
+}
@@ -0,0 +1,68 @@
+// Shared by the app and the race server binary, so it only depends on serde
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_RACE_PORT: u16 = 7878;
+
+/// What the app sends, one JSON object per line
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join { name: String },
+    Ready { ready: bool },
+    Progress { words: usize, correct_words: usize },
+    Finish { wpm: u16, accuracy: u16 },
+}
+
+/// What the server sends, one JSON object per line
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Lobby {
+        players: Vec<LobbyPlayer>,
+    },
+    /// Everyone generates the same words from the seed and the pack
+    Start {
+        seed: u64,
+        pack: String,
+        countdown_seconds: u64,
+    },
+    /// Live progress, the furthest racer first
+    Standings {
+        racers: Vec<Racer>,
+    },
+    /// The final ranking
+    Results {
+        racers: Vec<Racer>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LobbyPlayer {
+    pub name: String,
+    pub ready: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Racer {
+    pub name: String,
+    pub words: usize,
+    pub correct_words: usize,
+    /// Set once the racer finished
+    pub result: Option<RaceResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RaceResult {
+    pub wpm: u16,
+    pub accuracy: u16,
+}
+
+/// A message as the line that goes over the wire
+pub fn encode<T: Serialize>(message: &T) -> String {
+    let mut line = serde_json::to_string(message).unwrap_or_default();
+    line.push('\n');
+    line
+}
@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::text::grapheme_count;
+use crate::widgets::InputField;
+use crate::word_generator::{PlayerWordList, WordList, WordListIndex};
+use crate::word_packs::TextDirection;
+use crate::{AppState, GameTimer};
+
+/// Bumped whenever a field is added that older versions of the app can't do without
+const REPLAY_VERSION: u32 = 2;
+pub const PLAYBACK_SPEEDS: [f32; 3] = [1., 2., 4.];
+
+const REPLAYS_DIRECTORY: &str = "replays";
+/// Older replays of your own runs are deleted once there are more than this
+const MAX_SAVED_REPLAYS: usize = 50;
+
+/// The input field as it was right after a key changed it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Keystroke {
+    /// Seconds since the first keystroke, leaving out pauses
+    pub seconds: f32,
+    /// The word being typed
+    pub word: usize,
+    pub input: String,
+}
+
+/// The settings a replay was typed with, so it can be shown and compared like the original
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ReplaySettings {
+    pub word_pack: String,
+    pub direction: TextDirection,
+    /// The test duration, 0 for untimed runs
+    pub seconds: u16,
+    /// The tags the result was stored with
+    pub tags: Vec<String>,
+}
+
+/// A finished test, with everything needed to type against it again or play it back.
+/// Files without a version are from before the seed and settings were stored
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    #[serde(default = "first_replay_version")]
+    pub version: u32,
+    pub timestamp: u64,
+    pub mode: String,
+    pub wpm: u16,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub settings: ReplaySettings,
+    pub words: Vec<String>,
+    pub keystrokes: Vec<Keystroke>,
+}
+
+fn first_replay_version() -> u32 {
+    1
+}
+
+impl Replay {
+    pub fn new(
+        mode: &str,
+        wpm: u16,
+        word_list: &WordList,
+        settings: ReplaySettings,
+        keystrokes: &[Keystroke],
+    ) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            mode: mode.to_string(),
+            wpm,
+            seed: word_list.seed,
+            settings,
+            words: word_list.list.clone(),
+            keystrokes: keystrokes.to_vec(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|error| format!("The replay could not be read: {}", error))?;
+        let replay: Replay = serde_json::from_str(&json)
+            .map_err(|error| format!("The replay is not valid: {}", error))?;
+
+        if replay.version > REPLAY_VERSION {
+            return Err(format!(
+                "The replay is version {}, this app can only read up to version {}",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        if replay.words.is_empty() || replay.keystrokes.is_empty() {
+            return Err("The replay does not contain a run".to_string());
+        }
+        Ok(replay)
+    }
+
+    /// Saved under the replays directory so it can be raced later
+    pub fn save(&self) {
+        if fs::create_dir_all(REPLAYS_DIRECTORY).is_err() {
+            return;
+        }
+
+        // Named to the millisecond, so runs finished within the same second don't overwrite
+        // each other; older names are whole seconds and still sort in with these
+        let milliseconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+
+        if let Ok(json) = serde_json::to_string(self) {
+            let path = Path::new(REPLAYS_DIRECTORY).join(format!("{}.json", milliseconds));
+            let _ = fs::write(path, json);
+        }
+        prune_saved_replays();
+    }
+
+    /// The word and letter the run was at after this many seconds
+    pub fn position_at(&self, seconds: f32) -> (usize, usize) {
+        self.keystrokes
+            .iter()
+            .take_while(|keystroke| keystroke.seconds <= seconds)
+            .last()
+            .map(|keystroke| (keystroke.word, grapheme_count(&keystroke.input)))
+            .unwrap_or((0, 0))
+    }
+
+    /// How long the run lasted, as far as the keystrokes tell
+    pub fn duration(&self) -> f32 {
+        self.keystrokes
+            .last()
+            .map(|keystroke| keystroke.seconds)
+            .unwrap_or(0.)
+    }
+
+    pub fn description(&self) -> String {
+        format!("{} - {} WPM ({})", self.mode, self.wpm, self.timestamp)
+    }
+}
+
+/// Plays the keystrokes back up to this many seconds: the words moved on from and the input
+pub fn typed_words_at(keystrokes: &[Keystroke], seconds: f32) -> (Vec<String>, String) {
+    let mut playback = Playback::default();
+    playback.advance(keystrokes, seconds);
+    (playback.typed_words, playback.input)
+}
+
+/// Keystrokes played back so far, so moving on only goes over the ones since the last time
+#[derive(Default)]
+struct Playback {
+    next_keystroke: usize,
+    /// The words moved on from
+    typed_words: Vec<String>,
+    input: String,
+}
+
+impl Playback {
+    /// Plays the keystrokes up to this many seconds, returns whether any were played
+    fn advance(&mut self, keystrokes: &[Keystroke], seconds: f32) -> bool {
+        let start = self.next_keystroke;
+
+        for keystroke in keystrokes[start..]
+            .iter()
+            .take_while(|keystroke| keystroke.seconds <= seconds)
+        {
+            // Space moved on to the next word, backspace on an empty input went back to one
+            if keystroke.word > self.typed_words.len() {
+                self.typed_words.push(self.input.trim().to_string());
+            } else if keystroke.word < self.typed_words.len() {
+                self.typed_words.truncate(keystroke.word);
+            }
+            self.input = keystroke.input.clone();
+            self.next_keystroke += 1;
+        }
+
+        self.next_keystroke > start
+    }
+}
+
+/// Replays of your own runs, the newest first
+pub fn saved_replays() -> Vec<String> {
+    let mut paths: Vec<String> = fs::read_dir(REPLAYS_DIRECTORY)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Files are named after their timestamp, so this sorts them by age
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+fn prune_saved_replays() {
+    for path in saved_replays().iter().skip(MAX_SAVED_REPLAYS) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// A replay being played back in the word window
+pub struct ReplayViewer {
+    pub replay: Option<Replay>,
+    pub seconds: f32,
+    pub speed: f32,
+    pub playing: bool,
+    /// The path typed on the modes screen
+    pub path_input: String,
+    pub error: Option<String>,
+    /// The replay's words still have to replace the current test
+    needs_setup: bool,
+    /// Only goes forward; starting over plays back from the first keystroke again
+    playback: Playback,
+}
+
+impl Default for ReplayViewer {
+    fn default() -> Self {
+        Self {
+            replay: None,
+            seconds: 0.,
+            speed: PLAYBACK_SPEEDS[0],
+            playing: false,
+            path_input: String::new(),
+            error: None,
+            needs_setup: false,
+            playback: Playback::default(),
+        }
+    }
+}
+
+impl ReplayViewer {
+    pub fn open(&mut self) -> bool {
+        match Replay::load(self.path_input.trim()) {
+            Ok(replay) => {
+                self.replay = Some(replay);
+                self.error = None;
+                self.rewind();
+                true
+            }
+            Err(error) => {
+                self.error = Some(error);
+                false
+            }
+        }
+    }
+
+    pub fn rewind(&mut self) {
+        self.seconds = 0.;
+        self.playing = true;
+        self.needs_setup = true;
+        self.playback = Playback::default();
+    }
+}
+
+/// Plays the replay back by moving the test resources along, so the word window draws it
+/// exactly like a test being typed
+#[allow(clippy::too_many_arguments)]
+pub fn update_replay_viewer(
+    app_state: Res<State<AppState>>,
+    time: Res<Time>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut word_list: ResMut<WordList>,
+    mut player_word_list: ResMut<PlayerWordList>,
+    mut word_list_index: ResMut<WordListIndex>,
+    mut text_direction: ResMut<TextDirection>,
+    mut input_text: ResMut<InputField>,
+    mut game_timer: ResMut<GameTimer>,
+) {
+    if app_state.current() != &AppState::Viewing {
+        return;
+    }
+
+    let viewer = &mut *viewer;
+    let replay = match &viewer.replay {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    if viewer.needs_setup {
+        word_list.list = replay.words.clone();
+        word_list.seed = replay.seed;
+        *text_direction = replay.settings.direction;
+        word_list_index.current_index = 0;
+        player_word_list.list.clear();
+        input_text.text.clear();
+        input_text.enabled = false;
+        viewer.needs_setup = false;
+    }
+
+    if viewer.playing {
+        viewer.seconds += time.delta_seconds() * viewer.speed;
+
+        if viewer.seconds >= replay.duration() {
+            viewer.seconds = replay.duration();
+            viewer.playing = false;
+        }
+    }
+
+    if viewer.playback.advance(&replay.keystrokes, viewer.seconds) {
+        word_list_index.current_index = viewer.playback.typed_words.len();
+        player_word_list.list = viewer.playback.typed_words.clone();
+        input_text.text = viewer.playback.input.clone();
+    }
+    game_timer.elapsed_seconds = viewer.seconds as u32;
+}
@@ -0,0 +1,58 @@
+// Shared by the app and the result server tests, so it only depends on serde and ureq
+use serde::Serialize;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// How one attempt at emptying the queue went
+pub struct SendOutcome {
+    /// Results from the front of the queue that are done with, posted or refused
+    pub sent: usize,
+    pub refused: usize,
+    pub error: Option<String>,
+}
+
+/// Posts every result in the queue, with the token as a bearer token when there is one.
+/// Stops at the first result that couldn't be delivered so the queue keeps its order
+pub fn post_results<T: Serialize>(endpoint: &str, token: &str, queue: &[T]) -> SendOutcome {
+    let mut outcome = SendOutcome {
+        sent: 0,
+        refused: 0,
+        error: None,
+    };
+
+    for submitted in queue {
+        let json = match serde_json::to_string(submitted) {
+            Ok(json) => json,
+            Err(error) => {
+                outcome.error = Some(error.to_string());
+                return outcome;
+            }
+        };
+
+        let mut request = ureq::post(endpoint.trim())
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .set("Content-Type", "application/json");
+        if !token.trim().is_empty() {
+            request = request.set("Authorization", &format!("Bearer {}", token.trim()));
+        }
+
+        match request.send_string(&json) {
+            Ok(_) => outcome.sent += 1,
+            // The result itself is wrong, sending it again won't help
+            Err(ureq::Error::Status(400, _)) | Err(ureq::Error::Status(422, _)) => {
+                outcome.sent += 1;
+                outcome.refused += 1;
+            }
+            Err(ureq::Error::Status(code, _)) => {
+                outcome.error = Some(format!("the server answered with status {}", code));
+                return outcome;
+            }
+            Err(error) => {
+                outcome.error = Some(error.to_string());
+                return outcome;
+            }
+        }
+    }
+    outcome
+}
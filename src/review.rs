@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::text::grapheme_count;
+use crate::word_packs::WordPack;
+
+const REVIEW_QUEUE_FILE: &str = "review_queue.json";
+
+const SECONDS_PER_DAY: f32 = 86_400.;
+const STARTING_EASE: f32 = 2.5;
+const MINIMUM_EASE: f32 = 1.3;
+/// Words that come back this far apart are considered learned and leave the queue
+const LEARNED_INTERVAL_DAYS: f32 = 30.;
+/// A correct word is slow when its time per letter is this many times the test's average
+const SLOW_WORD_FACTOR: f32 = 1.75;
+
+/// How well a word was typed, as in the SM-2 spaced repetition algorithm
+#[derive(Copy, Clone, PartialEq)]
+enum Grade {
+    Missed,
+    Slow,
+    Good,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReviewItem {
+    pub word: String,
+    pub pack: String,
+    pub ease: f32,
+    pub interval_days: f32,
+    pub repetitions: u32,
+    pub due: u64,
+}
+
+impl ReviewItem {
+    fn new(word: &str, pack: &str, now: u64) -> Self {
+        Self {
+            word: word.to_string(),
+            pack: pack.to_string(),
+            ease: STARTING_EASE,
+            interval_days: 0.,
+            repetitions: 0,
+            due: now,
+        }
+    }
+
+    fn grade(&mut self, grade: Grade, now: u64) {
+        match grade {
+            // Missed words come back right away and start over
+            Grade::Missed => {
+                self.repetitions = 0;
+                self.interval_days = 0.;
+                self.ease = (self.ease - 0.2).max(MINIMUM_EASE);
+            }
+            Grade::Slow | Grade::Good => {
+                self.repetitions += 1;
+                self.interval_days = match self.repetitions {
+                    1 => 1.,
+                    2 => 6.,
+                    _ => self.interval_days * self.ease,
+                };
+
+                if grade == Grade::Slow {
+                    self.ease = (self.ease - 0.15).max(MINIMUM_EASE);
+                } else {
+                    self.ease += 0.1;
+                }
+            }
+        }
+        self.due = now + (self.interval_days * SECONDS_PER_DAY) as u64;
+    }
+}
+
+/// Words typed wrong or slowly, scheduled to come back until they are learned
+pub struct ReviewQueue {
+    items: Vec<ReviewItem>,
+}
+
+impl ReviewQueue {
+    pub fn load() -> Self {
+        let items = fs::read_to_string(REVIEW_QUEUE_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        Self { items }
+    }
+
+    /// The pack's words that are due, the longest overdue first
+    pub fn due_words(&self, pack: &str) -> Vec<String> {
+        self.due_words_at(pack, unix_time())
+    }
+
+    fn due_words_at(&self, pack: &str, now: u64) -> Vec<String> {
+        let mut due_items: Vec<&ReviewItem> = self
+            .items
+            .iter()
+            .filter(|item| item.pack == pack && item.due <= now)
+            .collect();
+
+        due_items.sort_by_key(|item| item.due);
+        due_items.iter().map(|item| item.word.clone()).collect()
+    }
+
+    /// Grades every word of a finished test; only words from the pack itself are kept,
+    /// so words changed by modifiers or transforms don't end up in the queue
+    pub fn record_test(
+        &mut self,
+        word_pack: &WordPack,
+        word_list: &[String],
+        player_word_list: &[String],
+        word_seconds: &[f32],
+    ) {
+        self.grade_test(
+            word_pack,
+            word_list,
+            player_word_list,
+            word_seconds,
+            unix_time(),
+        );
+        self.save();
+    }
+
+    fn grade_test(
+        &mut self,
+        word_pack: &WordPack,
+        word_list: &[String],
+        player_word_list: &[String],
+        word_seconds: &[f32],
+        now: u64,
+    ) {
+        let average_seconds_per_letter =
+            average_seconds_per_letter(word_list, player_word_list, word_seconds);
+
+        for ((word, typed), seconds) in word_list
+            .iter()
+            .zip(player_word_list.iter())
+            .zip(word_seconds.iter())
+        {
+            if !word_pack
+                .words
+                .iter()
+                .any(|pack_word| &pack_word.text == word)
+            {
+                continue;
+            }
+
+            let grade = if word != typed {
+                Grade::Missed
+            } else if average_seconds_per_letter > 0.
+                && seconds / grapheme_count(word) as f32
+                    > average_seconds_per_letter * SLOW_WORD_FACTOR
+            {
+                Grade::Slow
+            } else {
+                Grade::Good
+            };
+
+            let position = self
+                .items
+                .iter()
+                .position(|item| &item.word == word && item.pack == word_pack.id);
+
+            match position {
+                Some(position) => {
+                    let item = &mut self.items[position];
+
+                    // Typing a word before it is due doesn't move it along, missing it always counts
+                    if grade == Grade::Missed || item.due <= now {
+                        item.grade(grade, now);
+                    }
+                    if item.interval_days >= LEARNED_INTERVAL_DAYS {
+                        self.items.remove(position);
+                    }
+                }
+                None if grade != Grade::Good => {
+                    self.items.push(ReviewItem::new(word, &word_pack.id, now));
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn save(&self) {
+        if let Ok(json) = serde_json::to_string_pretty(&self.items) {
+            let _ = fs::write(REVIEW_QUEUE_FILE, json);
+        }
+    }
+}
+
+// Only correct words count, a mistyped word says nothing about speed
+fn average_seconds_per_letter(
+    word_list: &[String],
+    player_word_list: &[String],
+    word_seconds: &[f32],
+) -> f32 {
+    let mut seconds = 0.;
+    let mut letters = 0;
+
+    for ((word, typed), word_seconds) in word_list
+        .iter()
+        .zip(player_word_list.iter())
+        .zip(word_seconds.iter())
+    {
+        if word == typed {
+            seconds += word_seconds;
+            letters += grapheme_count(word);
+        }
+    }
+
+    if letters == 0 {
+        return 0.;
+    }
+    seconds / letters as f32
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word_packs::{PackWord, TextDirection};
+
+    const NOW: u64 = 1_000_000;
+    const DAY: u64 = SECONDS_PER_DAY as u64;
+
+    fn pack(words: &[&str]) -> WordPack {
+        WordPack {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            author: None,
+            license: None,
+            source: None,
+            language: "en".to_string(),
+            script: "Latin".to_string(),
+            direction: TextDirection::Ltr,
+            font: None,
+            words: words
+                .iter()
+                .map(|word| PackWord {
+                    text: word.to_string(),
+                    difficulty: None,
+                    frequency: 1,
+                })
+                .collect(),
+        }
+    }
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn good_words_come_back_further_apart() {
+        let mut item = ReviewItem::new("word", "test", NOW);
+
+        item.grade(Grade::Good, NOW);
+        assert_eq!(item.interval_days, 1.);
+        assert_eq!(item.due, NOW + DAY);
+
+        item.grade(Grade::Good, NOW);
+        assert_eq!(item.interval_days, 6.);
+
+        item.grade(Grade::Good, NOW);
+        assert!((item.interval_days - 6. * (STARTING_EASE + 0.2)).abs() < 0.001);
+        assert_eq!(item.repetitions, 3);
+    }
+
+    #[test]
+    fn missed_words_start_over_with_a_lower_ease() {
+        let mut item = ReviewItem::new("word", "test", NOW);
+        item.grade(Grade::Good, NOW);
+        item.grade(Grade::Good, NOW);
+
+        item.grade(Grade::Missed, NOW);
+        assert_eq!(item.repetitions, 0);
+        assert_eq!(item.due, NOW);
+        assert!((item.ease - (STARTING_EASE + 0.2 - 0.2)).abs() < 0.001);
+
+        for _ in 0..20 {
+            item.grade(Grade::Missed, NOW);
+        }
+        assert_eq!(item.ease, MINIMUM_EASE);
+    }
+
+    #[test]
+    fn queues_missed_and_slow_words_of_the_pack() {
+        let pack = pack(&["the", "and", "rhythm"]);
+        let mut queue = ReviewQueue { items: Vec::new() };
+
+        // A tenth of a second per letter, except for rhythm which takes a second per letter
+        let mut word_list = strings(&["the", "rhythm", "Extra"]);
+        let mut player_word_list = strings(&["teh", "rhythm", "extra"]);
+        let mut word_seconds = vec![0.3, 6.0, 0.5];
+        for _ in 0..6 {
+            word_list.push("and".to_string());
+            player_word_list.push("and".to_string());
+            word_seconds.push(0.3);
+        }
+
+        queue.grade_test(&pack, &word_list, &player_word_list, &word_seconds, NOW);
+
+        // Words that aren't in the pack, like ones a modifier changed, are left out
+        let mut queued = queue.due_words_at("test", NOW);
+        queued.sort();
+        assert_eq!(queued, strings(&["rhythm", "the"]));
+        assert!(queue.due_words_at("other", NOW).is_empty());
+    }
+
+    #[test]
+    fn only_due_words_move_along_and_learned_words_leave() {
+        let pack = pack(&["the"]);
+        let mut queue = ReviewQueue {
+            items: vec![ReviewItem::new("the", "test", NOW)],
+        };
+        queue.items[0].grade(Grade::Good, NOW);
+
+        // Typed well a day early, so it isn't rescheduled
+        queue.grade_test(&pack, &strings(&["the"]), &strings(&["the"]), &[0.3], NOW);
+        assert_eq!(queue.items[0].repetitions, 1);
+        assert!(queue.due_words_at("test", NOW).is_empty());
+        assert_eq!(queue.due_words_at("test", NOW + DAY), strings(&["the"]));
+
+        queue.items[0].repetitions = 3;
+        queue.items[0].interval_days = LEARNED_INTERVAL_DAYS;
+        queue.items[0].due = NOW;
+        queue.grade_test(&pack, &strings(&["the"]), &strings(&["the"]), &[0.3], NOW);
+        assert!(queue.items.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+const TEST_DURATION_SECONDS: u16 = 60;
+
+pub const DEFAULT_ACCURACY_THRESHOLD: u16 = 90;
+// Running accuracy swings wildly over the first few words, so it only counts after this many
+const MIN_CHARACTERS_FOR_ACCURACY: u16 = 20;
+
+/// How a test ends, independent of where its words come from
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RunMode {
+    Timed,
+    Zen,
+    SuddenDeath,
+    /// Fails once the running accuracy drops below this percentage
+    AccuracyThreshold(u16),
+}
+
+pub const ALL_RUN_MODES: [RunMode; 4] = [
+    RunMode::Timed,
+    RunMode::Zen,
+    RunMode::SuddenDeath,
+    RunMode::AccuracyThreshold(DEFAULT_ACCURACY_THRESHOLD),
+];
+
+/// Why a test ended early
+#[derive(Clone)]
+pub enum Failure {
+    WrongWord { expected: String, typed: String },
+    LowAccuracy { accuracy: u16, threshold: u16 },
+}
+
+impl Failure {
+    pub fn description(&self) -> String {
+        match self {
+            Failure::WrongWord { expected, typed } => {
+                format!("You typed \"{}\" instead of \"{}\"", typed, expected)
+            }
+            Failure::LowAccuracy {
+                accuracy,
+                threshold,
+            } => format!("Your accuracy fell to {}%, below {}%", accuracy, threshold),
+        }
+    }
+}
+
+impl RunMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunMode::Timed => "Timed",
+            RunMode::Zen => "Zen",
+            RunMode::SuddenDeath => "Sudden Death",
+            RunMode::AccuracyThreshold(_) => "Accuracy Threshold",
+        }
+    }
+
+    /// None when the test runs until the player stops it
+    pub fn duration(&self) -> Option<u16> {
+        match self {
+            RunMode::Zen => None,
+            _ => Some(TEST_DURATION_SECONDS),
+        }
+    }
+
+    pub fn tag(&self) -> Option<String> {
+        match self {
+            RunMode::Timed => None,
+            RunMode::Zen => Some("zen".to_string()),
+            RunMode::SuddenDeath => Some("sudden-death".to_string()),
+            RunMode::AccuracyThreshold(threshold) => Some(format!("accuracy-{}", threshold)),
+        }
+    }
+
+    /// Whether typing badly can end a test early
+    pub fn can_fail(&self) -> bool {
+        matches!(self, RunMode::SuddenDeath | RunMode::AccuracyThreshold(_))
+    }
+
+    /// Whether the rules of this mode were broken by the words typed so far
+    pub fn check_failure(
+        &self,
+        word_list: &[String],
+        player_word_list: &[String],
+        accuracy: u16,
+        characters_typed: u16,
+    ) -> Option<Failure> {
+        match self {
+            RunMode::SuddenDeath => word_list
+                .iter()
+                .zip(player_word_list.iter())
+                .find(|(word, typed)| word != typed)
+                .map(|(word, typed)| Failure::WrongWord {
+                    expected: word.clone(),
+                    typed: typed.clone(),
+                }),
+            RunMode::AccuracyThreshold(threshold) => {
+                if characters_typed >= MIN_CHARACTERS_FOR_ACCURACY && accuracy < *threshold {
+                    Some(Failure::LowAccuracy {
+                        accuracy,
+                        threshold: *threshold,
+                    })
+                } else {
+                    None
+                }
+            }
+            RunMode::Timed | RunMode::Zen => None,
+        }
+    }
+}
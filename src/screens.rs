@@ -0,0 +1,695 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Align, Color32, Label, Layout, RadioButton, RichText, ScrollArea, Ui};
+use bevy_egui::EguiContext;
+
+use crate::book::BookState;
+use crate::code_snippets::CodeState;
+use crate::config::UserConfig;
+use crate::game_mode::GameMode;
+use crate::hotkeys::{KeyBindings, Rebinding, ALL_ACTIONS};
+use crate::key_stats::KeyStats;
+use crate::pace::{PaceTarget, ALL_PACE_TARGETS, MAX_PACE_WPM, MIN_PACE_WPM};
+use crate::replay::{saved_replays, ReplayViewer};
+use crate::review::ReviewQueue;
+use crate::run_mode::{RunMode, ALL_RUN_MODES};
+use crate::transforms::ALL_TRANSFORMS;
+use crate::widgets::{StyledCentralPanel, StyledComboBox};
+use crate::word_filters::{MAX_WORD_LENGTH, MIN_WORD_LENGTH};
+use crate::word_packs::{WordPack, WordPacks};
+use crate::AppState;
+
+const CHAPTER_LIST_HEIGHT: f32 = 200.;
+
+/// What the modes screen offers to type besides random words
+struct ModeSources<'a> {
+    book_state: &'a mut BookState,
+    code_state: &'a mut CodeState,
+    viewer: &'a mut ReplayViewer,
+    word_pack: Option<&'a WordPack>,
+    due_word_count: usize,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_menu_screens(
+    mut app_state: ResMut<State<AppState>>,
+    mut game_mode: ResMut<GameMode>,
+    mut run_mode: ResMut<RunMode>,
+    mut book_state: ResMut<BookState>,
+    mut code_state: ResMut<CodeState>,
+    mut config: ResMut<UserConfig>,
+    word_packs: Res<WordPacks>,
+    review_queue: Res<ReviewQueue>,
+    keys: Res<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut ctx: ResMut<EguiContext>,
+    windows: Res<Windows>,
+) {
+    // Leaving the settings screen gives up on a half finished rebind
+    if app_state.current() != &AppState::Settings {
+        rebinding.action = None;
+    }
+    if app_state.current() != &AppState::Modes && app_state.current() != &AppState::Settings {
+        return;
+    }
+
+    if rebinding.capture(&keys, &mut config.key_bindings) {
+        config.save();
+    }
+
+    let window = windows.get_primary().unwrap();
+    let word_pack = word_packs.get(&config.word_pack);
+    let due_word_count = word_pack
+        .map(|word_pack| review_queue.due_words(&word_pack.id).len())
+        .unwrap_or(0);
+
+    let mut watch_replay = false;
+
+    StyledCentralPanel::new(window.width(), config.theme)
+        .central_panel()
+        .show(ctx.ctx_mut(), |ui| {
+            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                if app_state.current() == &AppState::Modes {
+                    watch_replay = draw_modes_screen(
+                        ui,
+                        &mut game_mode,
+                        &mut run_mode,
+                        &mut config,
+                        ModeSources {
+                            book_state: &mut book_state,
+                            code_state: &mut code_state,
+                            viewer: &mut viewer,
+                            word_pack,
+                            due_word_count,
+                        },
+                    );
+                } else {
+                    draw_settings_screen(ui, &mut config, &mut rebinding);
+                }
+            });
+        });
+
+    if watch_replay {
+        app_state.set(AppState::Viewing).unwrap();
+    }
+}
+
+fn draw_modes_screen(
+    ui: &mut Ui,
+    game_mode: &mut GameMode,
+    run_mode: &mut RunMode,
+    config: &mut UserConfig,
+    sources: ModeSources,
+) -> bool {
+    let ModeSources {
+        book_state,
+        code_state,
+        viewer,
+        word_pack,
+        due_word_count,
+    } = sources;
+
+    ui.add(Label::new(
+        RichText::new("MODES").heading().color(Color32::GREEN),
+    ));
+    ui.add_space(40.);
+
+    ui.horizontal(|ui| {
+        for mode in ALL_RUN_MODES {
+            // The threshold can be changed, so only the kind of mode is compared
+            let selected = std::mem::discriminant(run_mode) == std::mem::discriminant(&mode);
+
+            if ui.radio(selected, mode.name()).clicked() && !selected {
+                *run_mode = mode;
+            }
+        }
+    });
+    if let RunMode::AccuracyThreshold(threshold) = run_mode {
+        ui.add(egui::Slider::new(threshold, 50..=100).text("% accuracy needed"));
+    }
+    draw_typing_toggles(ui, config);
+    draw_pace_section(ui, config);
+    ui.add_space(20.);
+
+    ui.radio_value(game_mode, GameMode::Words, GameMode::Words.name());
+    draw_modifier_toggles(ui, config);
+    ui.radio_value(game_mode, GameMode::Numbers, GameMode::Numbers.name());
+    ui.radio_value(game_mode, GameMode::Symbols, GameMode::Symbols.name());
+
+    // A book has to be loaded before it can be typed
+    let book_radio = ui.add_enabled(
+        book_state.book().is_some(),
+        RadioButton::new(*game_mode == GameMode::Book, GameMode::Book.name()),
+    );
+    if book_radio.clicked() {
+        *game_mode = GameMode::Book;
+    }
+
+    let code_radio = ui.add_enabled(
+        !code_state.snippets().is_empty(),
+        RadioButton::new(*game_mode == GameMode::Code, GameMode::Code.name()),
+    );
+    if code_radio.clicked() {
+        *game_mode = GameMode::Code;
+    }
+
+    // Review drains the words of the selected language that are due
+    let review_radio = ui.add_enabled(
+        due_word_count > 0,
+        RadioButton::new(
+            *game_mode == GameMode::Review,
+            format!("{} ({} due)", GameMode::Review.name(), due_word_count),
+        ),
+    );
+    if review_radio.clicked() {
+        *game_mode = GameMode::Review;
+    }
+
+    ui.add_space(40.);
+
+    let mut watch_replay = false;
+    ScrollArea::vertical()
+        .id_source("mode_sources")
+        .show(ui, |ui| {
+            draw_filter_section(ui, config, word_pack);
+
+            ui.add_space(40.);
+            draw_book_section(ui, book_state);
+
+            ui.add_space(40.);
+            draw_code_section(ui, code_state, config);
+
+            ui.add_space(40.);
+            watch_replay = draw_replay_section(ui, viewer);
+        });
+
+    // Switch back if the source of the selected mode went away
+    if (*game_mode == GameMode::Book && book_state.book().is_none())
+        || (*game_mode == GameMode::Code && code_state.snippets().is_empty())
+        || (*game_mode == GameMode::Review && due_word_count == 0)
+    {
+        *game_mode = GameMode::Words;
+    }
+    watch_replay
+}
+
+fn draw_typing_toggles(ui: &mut Ui, config: &mut UserConfig) {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(&mut config.strict_mode, "Strict")
+            .on_hover_text("Space only moves on once the word is right")
+            .changed();
+        changed |= ui
+            .checkbox(&mut config.stop_on_letter, "Stop on Letter")
+            .on_hover_text("Wrong letters are blocked")
+            .changed();
+    });
+
+    if changed {
+        config.save();
+    }
+}
+
+fn draw_pace_section(ui: &mut Ui, config: &mut UserConfig) {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Pace Caret");
+
+        for target in ALL_PACE_TARGETS {
+            // The target speed can be changed, so only the kind of target is compared
+            let selected = std::mem::discriminant(&config.pace) == std::mem::discriminant(&target);
+
+            if ui.radio(selected, target.name()).clicked() && !selected {
+                config.pace = target;
+                changed = true;
+            }
+        }
+    });
+    if let PaceTarget::Wpm(wpm) = &mut config.pace {
+        changed |= ui
+            .add(egui::Slider::new(wpm, MIN_PACE_WPM..=MAX_PACE_WPM).text("WPM"))
+            .changed();
+    }
+    // Your own runs are saved as replays, a teammate's replay file can be raced by its path
+    if let PaceTarget::Ghost(path) = &mut config.pace {
+        ui.horizontal(|ui| {
+            StyledComboBox::new("ghost_replay", "Your runs")
+                .combo_box()
+                .show_ui(ui, |ui| {
+                    for replay_path in saved_replays() {
+                        changed |= ui
+                            .selectable_value(path, replay_path.clone(), replay_path)
+                            .changed();
+                    }
+                });
+            changed |= ui.text_edit_singleline(path).changed();
+        });
+        ui.add(Label::new(
+            RichText::new("The ghost's words replace the generated ones").color(Color32::YELLOW),
+        ));
+    }
+
+    if changed {
+        config.save();
+    }
+}
+
+fn draw_modifier_toggles(ui: &mut Ui, config: &mut UserConfig) {
+    let modifiers = &mut config.modifiers;
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(&mut modifiers.punctuation, "Punctuation")
+            .changed();
+        changed |= ui.checkbox(&mut modifiers.quotes, "Quotes").changed();
+        changed |= ui
+            .checkbox(&mut modifiers.parentheses, "Parentheses")
+            .changed();
+        changed |= ui.checkbox(&mut modifiers.numbers, "Numbers").changed();
+    });
+
+    if changed {
+        config.save();
+    }
+}
+
+fn draw_filter_section(ui: &mut Ui, config: &mut UserConfig, word_pack: Option<&WordPack>) {
+    ui.heading("Word Filters");
+    ui.add(Label::new(
+        RichText::new("Which words of the language pack random word tests use")
+            .color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    let filters_before = config.word_filters.clone();
+    let filters = &mut config.word_filters;
+
+    egui::Grid::new("word_filters").show(ui, |ui| {
+        ui.label("Shortest");
+        ui.add(egui::Slider::new(
+            &mut filters.min_length,
+            MIN_WORD_LENGTH..=MAX_WORD_LENGTH,
+        ));
+        ui.end_row();
+
+        ui.label("Longest");
+        let max_length_text = filters.max_length_text();
+        ui.add(
+            egui::Slider::new(&mut filters.max_length, MIN_WORD_LENGTH..=MAX_WORD_LENGTH)
+                .text(max_length_text),
+        );
+        ui.end_row();
+
+        ui.label("Including any of");
+        ui.text_edit_singleline(&mut filters.include_letters);
+        ui.end_row();
+
+        ui.label("Excluding");
+        ui.text_edit_singleline(&mut filters.exclude_letters);
+        ui.end_row();
+    });
+
+    if filters.max_length < filters.min_length {
+        filters.max_length = filters.min_length;
+    }
+
+    if let Some(word_pack) = word_pack {
+        let difficulty_tags = word_pack.difficulty_tags();
+
+        // No tier checked means every tier is used
+        if !difficulty_tags.is_empty() {
+            ui.horizontal(|ui| {
+                for difficulty in difficulty_tags {
+                    let mut checked = filters.difficulties.contains(&difficulty);
+
+                    if ui.checkbox(&mut checked, difficulty.as_str()).changed() {
+                        if checked {
+                            filters.difficulties.push(difficulty);
+                        } else {
+                            filters.difficulties.retain(|tier| *tier != difficulty);
+                        }
+                    }
+                }
+            });
+        }
+
+        let matcher = filters.matcher();
+        let matching_words = word_pack
+            .words
+            .iter()
+            .filter(|word| matcher.matches(word))
+            .count();
+
+        if matching_words == 0 {
+            ui.add(Label::new(
+                RichText::new("No words match, the filters will be ignored").color(Color32::RED),
+            ));
+        } else {
+            ui.add(Label::new(
+                RichText::new(format!(
+                    "{} of {} words match",
+                    matching_words,
+                    word_pack.words.len()
+                ))
+                .color(Color32::YELLOW),
+            ));
+        }
+    }
+
+    if *filters != filters_before {
+        config.save();
+    }
+}
+
+fn draw_book_section(ui: &mut Ui, book_state: &mut BookState) {
+    ui.heading("Book");
+    ui.add(Label::new(
+        RichText::new("Path to a plain text file").color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut book_state.path_input);
+
+        if ui.button("LOAD").clicked() {
+            book_state.load_from_input();
+        }
+    });
+
+    if let Some(load_error) = &book_state.load_error {
+        ui.add(Label::new(RichText::new(load_error).color(Color32::RED)));
+    }
+
+    if let Some(book) = book_state.book() {
+        ui.add_space(20.);
+        ui.heading(book.title.as_str());
+        ui.add(Label::new(
+            RichText::new(book_state.position_text()).color(Color32::YELLOW),
+        ));
+        if book_state.progress().finished {
+            ui.add(Label::new(
+                RichText::new("You have typed the whole book").color(Color32::GREEN),
+            ));
+        }
+        ui.add_space(20.);
+
+        ScrollArea::vertical()
+            .id_source("chapter_list")
+            .max_height(CHAPTER_LIST_HEIGHT)
+            .show(ui, |ui| {
+                egui::Grid::new("chapter_stats")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Chapter");
+                        ui.label("Sessions");
+                        ui.label("WPM");
+                        ui.label("Accuracy");
+                        ui.end_row();
+
+                        for (chapter, stats) in book
+                            .chapters
+                            .iter()
+                            .zip(book_state.progress().chapter_stats.iter())
+                        {
+                            ui.label(chapter.title.as_str());
+                            ui.label(stats.sessions.to_string());
+                            ui.label(stats.wpm().to_string());
+                            ui.label(format!("{}%", stats.accuracy()));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+fn draw_code_section(ui: &mut Ui, code_state: &mut CodeState, config: &mut UserConfig) {
+    ui.heading("Code Snippets");
+    ui.add(Label::new(
+        RichText::new("Functions taken from a source directory").color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    egui::Grid::new("code_source").show(ui, |ui| {
+        ui.label("Directory");
+        ui.text_edit_singleline(&mut code_state.directory_input);
+        ui.end_row();
+
+        ui.label("Extensions");
+        ui.text_edit_singleline(&mut code_state.extensions_input);
+        ui.end_row();
+
+        ui.label("Ignore");
+        ui.text_edit_singleline(&mut code_state.ignore_input);
+        ui.end_row();
+    });
+
+    if ui.button("SCAN").clicked() {
+        config.code_source = code_state.config_from_input();
+        config.save();
+        code_state.scan(&config.code_source);
+    }
+
+    if let Some(scan_error) = &code_state.scan_error {
+        ui.add(Label::new(RichText::new(scan_error).color(Color32::RED)));
+    } else if !code_state.snippets().is_empty() {
+        ui.add(Label::new(
+            RichText::new(format!("{} snippets found", code_state.snippets().len()))
+                .color(Color32::GREEN),
+        ));
+    }
+}
+
+/// Opens a replay file to play back in the word window
+fn draw_replay_section(ui: &mut Ui, viewer: &mut ReplayViewer) -> bool {
+    let mut watch_replay = false;
+
+    ui.heading("Replays");
+    ui.add(Label::new(
+        RichText::new("Watch one of your runs, or a replay file someone sent you")
+            .color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    ui.horizontal(|ui| {
+        StyledComboBox::new("watch_replay", "Your runs")
+            .combo_box()
+            .show_ui(ui, |ui| {
+                for replay_path in saved_replays() {
+                    ui.selectable_value(&mut viewer.path_input, replay_path.clone(), replay_path);
+                }
+            });
+        ui.text_edit_singleline(&mut viewer.path_input);
+
+        if ui.button("WATCH").clicked() {
+            watch_replay = viewer.open();
+        }
+    });
+
+    if let Some(error) = &viewer.error {
+        ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+    }
+    watch_replay
+}
+
+fn draw_settings_screen(ui: &mut Ui, config: &mut UserConfig, rebinding: &mut Rebinding) {
+    ui.add(Label::new(
+        RichText::new("SETTINGS").heading().color(Color32::GREEN),
+    ));
+    ui.add_space(40.);
+
+    ui.heading("Transforms");
+    ui.add(Label::new(
+        RichText::new("Applied to random words in this order").color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    let mut changed = false;
+    let mut move_up = None;
+    let mut remove = None;
+
+    egui::Grid::new("transform_pipeline").show(ui, |ui| {
+        for (index, transform) in config.transforms.iter().enumerate() {
+            ui.label(format!("{}. {}", index + 1, transform.name()));
+
+            if ui.add_enabled(index > 0, egui::Button::new("UP")).clicked() {
+                move_up = Some(index);
+            }
+            if ui.button("REMOVE").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(index) = move_up {
+        config.transforms.swap(index, index - 1);
+        changed = true;
+    }
+    if let Some(index) = remove {
+        config.transforms.remove(index);
+        changed = true;
+    }
+
+    ui.add_space(20.);
+
+    for transform in ALL_TRANSFORMS {
+        if config.transforms.contains(&transform) {
+            continue;
+        }
+        if ui.button(format!("ADD {}", transform.name())).clicked() {
+            config.transforms.push(transform);
+            changed = true;
+        }
+    }
+
+    if changed {
+        config.save();
+    }
+
+    ui.add_space(40.);
+    ScrollArea::vertical()
+        .id_source("settings_sections")
+        .show(ui, |ui| {
+            draw_key_binding_section(ui, config, rebinding);
+            ui.add_space(40.);
+            draw_leaderboard_section(ui, config);
+            ui.add_space(40.);
+            draw_submission_section(ui, config);
+        });
+}
+
+fn draw_key_binding_section(ui: &mut Ui, config: &mut UserConfig, rebinding: &mut Rebinding) {
+    ui.heading("Shortcuts");
+    ui.add(Label::new(
+        RichText::new("Press CHANGE, then the new keys. Escape cancels").color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    egui::Grid::new("key_bindings").show(ui, |ui| {
+        for action in ALL_ACTIONS {
+            ui.label(action.name());
+
+            if rebinding.action == Some(action) {
+                ui.add(Label::new(
+                    RichText::new("Press a key...").color(Color32::GREEN),
+                ));
+            } else {
+                ui.label(config.key_bindings.get(action).text());
+            }
+
+            if ui.button("CHANGE").clicked() {
+                rebinding.action = Some(action);
+                rebinding.error = None;
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(error) = &rebinding.error {
+        ui.add(Label::new(RichText::new(error).color(Color32::RED)));
+    }
+    // Only a hand edited config can get here, rebinding refuses keys that are taken
+    for conflict in config.key_bindings.conflicts() {
+        ui.add(Label::new(RichText::new(conflict).color(Color32::RED)));
+    }
+
+    ui.add_space(10.);
+    if ui.button("RESET DEFAULTS").clicked() {
+        config.key_bindings = KeyBindings::default();
+        rebinding.action = None;
+        rebinding.error = None;
+        config.save();
+    }
+}
+
+fn draw_leaderboard_section(ui: &mut Ui, config: &mut UserConfig) {
+    ui.heading("Leaderboard");
+    ui.add(Label::new(
+        RichText::new("Results are shared with everyone using the same directory")
+            .color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    let mut changed = false;
+
+    egui::Grid::new("leaderboard_settings").show(ui, |ui| {
+        ui.label("Directory");
+        changed |= ui
+            .text_edit_singleline(&mut config.leaderboard.directory)
+            .changed();
+        ui.end_row();
+
+        ui.label("Name");
+        changed |= ui
+            .text_edit_singleline(&mut config.leaderboard.name)
+            .changed();
+        ui.end_row();
+    });
+
+    if changed {
+        config.save();
+    }
+}
+
+fn draw_submission_section(ui: &mut Ui, config: &mut UserConfig) {
+    ui.heading("Result Submission");
+    ui.add(Label::new(
+        RichText::new("Finished results are posted here, and retried while it can't be reached")
+            .color(Color32::YELLOW),
+    ));
+    ui.add_space(10.);
+
+    let mut changed = false;
+
+    egui::Grid::new("submission_settings").show(ui, |ui| {
+        ui.label("Endpoint");
+        changed |= ui
+            .text_edit_singleline(&mut config.submission.endpoint)
+            .changed();
+        ui.end_row();
+
+        ui.label("Token");
+        changed |= ui
+            .add(egui::TextEdit::singleline(&mut config.submission.token).password(true))
+            .changed();
+        ui.end_row();
+    });
+
+    if changed {
+        config.save();
+    }
+}
+
+/// Accuracy per key for this test next to the accuracy over every test of the same mode
+pub fn draw_key_stats(ui: &mut Ui, key_stats: &KeyStats, all_time_key_stats: &KeyStats) {
+    ScrollArea::horizontal()
+        .id_source("key_stats")
+        .show(ui, |ui| {
+            egui::Grid::new("key_stats_grid").show(ui, |ui| {
+                ui.label("Key");
+                for key in key_stats.keys() {
+                    ui.add(Label::new(
+                        RichText::new(key.as_str()).color(Color32::YELLOW),
+                    ));
+                }
+                ui.end_row();
+
+                ui.label("Test");
+                for count in key_stats.values() {
+                    ui.label(format!("{}%", count.accuracy()));
+                }
+                ui.end_row();
+
+                ui.label("All time");
+                for key in key_stats.keys() {
+                    let all_time = all_time_key_stats.get(key).copied().unwrap_or_default();
+                    ui.label(format!("{}%", all_time.accuracy()));
+                }
+                ui.end_row();
+            });
+        });
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::UserConfig;
+use crate::history::TestResult;
+use crate::inbox::Inbox;
+use crate::result_posting::{post_results, SendOutcome};
+
+/// Results that haven't reached the endpoint yet, kept so they survive a restart
+const QUEUE_FILE: &str = "submission_queue.jsonl";
+/// The wait before the first retry, doubled after every failed attempt
+const FIRST_RETRY_SECONDS: u64 = 15;
+const MAX_RETRY_SECONDS: u64 = 600;
+
+/// What is posted to the endpoint, a result from the history with who typed it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SubmittedResult {
+    /// The same on every attempt, so a server that stored a result whose answer never arrived
+    /// can tell the retry is a duplicate
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub accuracy: u16,
+    #[serde(flatten)]
+    pub result: TestResult,
+}
+
+/// Posts finished results in the background and keeps the ones that couldn't be posted
+pub struct ResultSubmitter {
+    queue: Vec<SubmittedResult>,
+    sending: Option<Inbox<SendOutcome>>,
+    next_attempt: Instant,
+    retry_seconds: u64,
+    /// How the last attempt went, shown with the results of a test
+    pub status: Option<String>,
+}
+
+impl ResultSubmitter {
+    /// Whatever was still queued when the app closed is tried again right away
+    pub fn load() -> Self {
+        let mut queue: Vec<SubmittedResult> = fs::read_to_string(QUEUE_FILE)
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Queued before results had an id
+        for submitted in queue.iter_mut().filter(|submitted| submitted.id.is_empty()) {
+            submitted.id = submission_id(&submitted.name, &submitted.result);
+        }
+
+        Self {
+            queue,
+            sending: None,
+            next_attempt: Instant::now(),
+            retry_seconds: FIRST_RETRY_SECONDS,
+            status: None,
+        }
+    }
+
+    /// Called when a test ends; the result is only queued when there is an endpoint
+    pub fn submit(&mut self, config: &UserConfig, result: &TestResult, accuracy: u16) {
+        if config.submission.endpoint.trim().is_empty() {
+            return;
+        }
+
+        let name = config.leaderboard.player_name();
+        self.queue.push(SubmittedResult {
+            id: submission_id(&name, result),
+            name,
+            accuracy,
+            result: result.clone(),
+        });
+        self.save_queue();
+        self.status = Some("Submitting".to_string());
+
+        // A new result is a good moment to find out if the endpoint is back
+        self.next_attempt = Instant::now();
+        self.retry_seconds = FIRST_RETRY_SECONDS;
+    }
+
+    fn save_queue(&self) {
+        let lines: Vec<String> = self
+            .queue
+            .iter()
+            .filter_map(|submitted| serde_json::to_string(submitted).ok())
+            .collect();
+
+        if lines.is_empty() {
+            let _ = fs::remove_file(QUEUE_FILE);
+        } else {
+            let _ = fs::write(QUEUE_FILE, lines.join("\n") + "\n");
+        }
+    }
+
+    fn finish_attempt(&mut self, outcome: SendOutcome) {
+        self.queue.drain(..outcome.sent.min(self.queue.len()));
+        self.save_queue();
+
+        self.status = match (&outcome.error, outcome.refused) {
+            (Some(error), _) => Some(format!(
+                "Not submitted yet, {} in the queue: {}",
+                self.queue.len(),
+                error
+            )),
+            (None, 0) => Some("Submitted".to_string()),
+            (None, refused) => Some(format!("The server refused {} results", refused)),
+        };
+
+        if outcome.error.is_some() {
+            self.next_attempt = Instant::now() + Duration::from_secs(self.retry_seconds);
+            self.retry_seconds = (self.retry_seconds * 2).min(MAX_RETRY_SECONDS);
+        } else {
+            self.retry_seconds = FIRST_RETRY_SECONDS;
+        }
+    }
+}
+
+/// Empties the queue on a background thread, and retries later when the endpoint can't be
+/// reached
+pub fn send_submissions(config: Res<UserConfig>, mut submitter: ResMut<ResultSubmitter>) {
+    if let Some(sending) = &submitter.sending {
+        let outcome = match sending.try_take() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => SendOutcome {
+                sent: 0,
+                refused: 0,
+                error: Some("the submission was interrupted".to_string()),
+            },
+        };
+        submitter.sending = None;
+        submitter.finish_attempt(outcome);
+        return;
+    }
+
+    if submitter.queue.is_empty()
+        || config.submission.endpoint.trim().is_empty()
+        || Instant::now() < submitter.next_attempt
+    {
+        return;
+    }
+
+    let queue = submitter.queue.clone();
+    let submission = config.submission.clone();
+    let (sender, sending) = Inbox::channel();
+
+    thread::spawn(move || {
+        let _ = sender.send(post_results(
+            &submission.endpoint,
+            &submission.token,
+            &queue,
+        ));
+    });
+    submitter.sending = Some(sending);
+}
+
+/// Who typed it and when, with a random part for two results finished in the same second
+fn submission_id(name: &str, result: &TestResult) -> String {
+    format!(
+        "{}-{}-{:08x}",
+        name,
+        result.timestamp,
+        rand::random::<u32>()
+    )
+}
@@ -0,0 +1,170 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::word_packs::TextDirection;
+
+/// The letters of a word as the player sees them; an accented letter or a letter with
+/// combining marks is a single grapheme even when it is made of several chars
+pub fn graphemes(text: &str) -> Vec<&str> {
+    text.graphemes(true).collect()
+}
+
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// How an Arabic letter connects to the letters next to it
+#[derive(Copy, Clone, PartialEq)]
+enum Joining {
+    /// Connects on both sides
+    Dual,
+    /// Only connects to the letter before it
+    Right,
+    None,
+}
+
+/// The isolated, final, initial and medial presentation forms of the Arabic letters; letters
+/// that only join the letter before them have no initial or medial form
+const ARABIC_FORMS: [(char, [char; 4]); 36] = [
+    ('\u{0621}', ['\u{FE80}', '\u{FE80}', '\u{FE80}', '\u{FE80}']),
+    ('\u{0622}', ['\u{FE81}', '\u{FE82}', '\u{FE81}', '\u{FE82}']),
+    ('\u{0623}', ['\u{FE83}', '\u{FE84}', '\u{FE83}', '\u{FE84}']),
+    ('\u{0624}', ['\u{FE85}', '\u{FE86}', '\u{FE85}', '\u{FE86}']),
+    ('\u{0625}', ['\u{FE87}', '\u{FE88}', '\u{FE87}', '\u{FE88}']),
+    ('\u{0626}', ['\u{FE89}', '\u{FE8A}', '\u{FE8B}', '\u{FE8C}']),
+    ('\u{0627}', ['\u{FE8D}', '\u{FE8E}', '\u{FE8D}', '\u{FE8E}']),
+    ('\u{0628}', ['\u{FE8F}', '\u{FE90}', '\u{FE91}', '\u{FE92}']),
+    ('\u{0629}', ['\u{FE93}', '\u{FE94}', '\u{FE93}', '\u{FE94}']),
+    ('\u{062A}', ['\u{FE95}', '\u{FE96}', '\u{FE97}', '\u{FE98}']),
+    ('\u{062B}', ['\u{FE99}', '\u{FE9A}', '\u{FE9B}', '\u{FE9C}']),
+    ('\u{062C}', ['\u{FE9D}', '\u{FE9E}', '\u{FE9F}', '\u{FEA0}']),
+    ('\u{062D}', ['\u{FEA1}', '\u{FEA2}', '\u{FEA3}', '\u{FEA4}']),
+    ('\u{062E}', ['\u{FEA5}', '\u{FEA6}', '\u{FEA7}', '\u{FEA8}']),
+    ('\u{062F}', ['\u{FEA9}', '\u{FEAA}', '\u{FEA9}', '\u{FEAA}']),
+    ('\u{0630}', ['\u{FEAB}', '\u{FEAC}', '\u{FEAB}', '\u{FEAC}']),
+    ('\u{0631}', ['\u{FEAD}', '\u{FEAE}', '\u{FEAD}', '\u{FEAE}']),
+    ('\u{0632}', ['\u{FEAF}', '\u{FEB0}', '\u{FEAF}', '\u{FEB0}']),
+    ('\u{0633}', ['\u{FEB1}', '\u{FEB2}', '\u{FEB3}', '\u{FEB4}']),
+    ('\u{0634}', ['\u{FEB5}', '\u{FEB6}', '\u{FEB7}', '\u{FEB8}']),
+    ('\u{0635}', ['\u{FEB9}', '\u{FEBA}', '\u{FEBB}', '\u{FEBC}']),
+    ('\u{0636}', ['\u{FEBD}', '\u{FEBE}', '\u{FEBF}', '\u{FEC0}']),
+    ('\u{0637}', ['\u{FEC1}', '\u{FEC2}', '\u{FEC3}', '\u{FEC4}']),
+    ('\u{0638}', ['\u{FEC5}', '\u{FEC6}', '\u{FEC7}', '\u{FEC8}']),
+    ('\u{0639}', ['\u{FEC9}', '\u{FECA}', '\u{FECB}', '\u{FECC}']),
+    ('\u{063A}', ['\u{FECD}', '\u{FECE}', '\u{FECF}', '\u{FED0}']),
+    ('\u{0641}', ['\u{FED1}', '\u{FED2}', '\u{FED3}', '\u{FED4}']),
+    ('\u{0642}', ['\u{FED5}', '\u{FED6}', '\u{FED7}', '\u{FED8}']),
+    ('\u{0643}', ['\u{FED9}', '\u{FEDA}', '\u{FEDB}', '\u{FEDC}']),
+    ('\u{0644}', ['\u{FEDD}', '\u{FEDE}', '\u{FEDF}', '\u{FEE0}']),
+    ('\u{0645}', ['\u{FEE1}', '\u{FEE2}', '\u{FEE3}', '\u{FEE4}']),
+    ('\u{0646}', ['\u{FEE5}', '\u{FEE6}', '\u{FEE7}', '\u{FEE8}']),
+    ('\u{0647}', ['\u{FEE9}', '\u{FEEA}', '\u{FEEB}', '\u{FEEC}']),
+    ('\u{0648}', ['\u{FEED}', '\u{FEEE}', '\u{FEED}', '\u{FEEE}']),
+    ('\u{0649}', ['\u{FEEF}', '\u{FEF0}', '\u{FEEF}', '\u{FEF0}']),
+    ('\u{064A}', ['\u{FEF1}', '\u{FEF2}', '\u{FEF3}', '\u{FEF4}']),
+];
+const ARABIC_TATWEEL: char = '\u{0640}';
+
+fn joining(letter: &str) -> Joining {
+    let base = match letter.chars().next() {
+        Some(base) => base,
+        None => return Joining::None,
+    };
+    if base == ARABIC_TATWEEL {
+        return Joining::Dual;
+    }
+
+    match ARABIC_FORMS.iter().find(|(letter, _)| *letter == base) {
+        // Hamza stands on its own
+        Some(('\u{0621}', _)) => Joining::None,
+        Some((_, forms)) if forms[2] == forms[0] => Joining::Right,
+        Some(_) => Joining::Dual,
+        None => Joining::None,
+    }
+}
+
+/// The letters of a word as they are drawn. The UI has no text shaping, so every Arabic letter
+/// is swapped for the presentation form that joins it to its neighbours. Marks stay on their
+/// letter, and there is still one entry per grapheme so letters can be colored one by one.
+pub fn shaped_letters(text: &str) -> Vec<String> {
+    let letters = graphemes(text);
+    let joinings: Vec<Joining> = letters.iter().map(|letter| joining(letter)).collect();
+
+    letters
+        .iter()
+        .enumerate()
+        .map(|(index, letter)| {
+            let mut chars = letter.chars();
+            let base = chars.next().unwrap_or_default();
+            let forms = match ARABIC_FORMS.iter().find(|(letter, _)| *letter == base) {
+                Some((_, forms)) => forms,
+                None => return letter.to_string(),
+            };
+
+            let joins_previous = index > 0
+                && joinings[index - 1] == Joining::Dual
+                && joinings[index] != Joining::None;
+            let joins_next = joinings[index] == Joining::Dual
+                && index + 1 < letters.len()
+                && joinings[index + 1] != Joining::None;
+
+            let form = match (joins_previous, joins_next) {
+                (false, false) => forms[0],
+                (true, false) => forms[1],
+                (false, true) => forms[2],
+                (true, true) => forms[3],
+            };
+            std::iter::once(form).chain(chars).collect()
+        })
+        .collect()
+}
+
+/// Labels are always drawn left to right, so right to left text is handed over reversed
+pub fn visual_order(text: &str, text_direction: TextDirection) -> String {
+    match text_direction {
+        TextDirection::Ltr => text.to_string(),
+        TextDirection::Rtl => shaped_letters(text).into_iter().rev().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_arabic_letters_to_their_neighbours() {
+        // Beh, yeh and teh all join on both sides
+        assert_eq!(
+            shaped_letters("بيت"),
+            vec!["\u{FE91}", "\u{FEF4}", "\u{FE96}"]
+        );
+        // Meem starts the word, noon ends it
+        assert_eq!(shaped_letters("من"), vec!["\u{FEE3}", "\u{FEE6}"]);
+    }
+
+    #[test]
+    fn letters_after_a_right_joining_letter_start_over() {
+        // Dal and alef never join the letter after them
+        assert_eq!(
+            shaped_letters("دار"),
+            vec!["\u{FEA9}", "\u{FE8D}", "\u{FEAD}"]
+        );
+        assert_eq!(
+            shaped_letters("ولد"),
+            vec!["\u{FEED}", "\u{FEDF}", "\u{FEAA}"]
+        );
+    }
+
+    #[test]
+    fn keeps_marks_and_other_scripts() {
+        assert_eq!(shaped_letters("بَ"), vec!["\u{FE8F}\u{064E}"]);
+        assert_eq!(shaped_letters("café"), vec!["c", "a", "f", "é"]);
+        assert_eq!(shaped_letters("שלום"), vec!["ש", "ל", "ו", "ם"]);
+    }
+
+    #[test]
+    fn right_to_left_text_is_reversed_after_shaping() {
+        assert_eq!(visual_order("من", TextDirection::Rtl), "\u{FEE6}\u{FEE3}");
+        assert_eq!(visual_order("שלום", TextDirection::Rtl), "םולש");
+        assert_eq!(visual_order("café", TextDirection::Ltr), "café");
+    }
+}
@@ -1,21 +1,65 @@
 use crate::colors;
 use bevy_egui::egui::{Color32, Visuals};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ThemeKind {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeKind {
+    pub fn toggled(&self) -> Self {
+        match self {
+            ThemeKind::Dark => ThemeKind::Light,
+            ThemeKind::Light => ThemeKind::Dark,
+        }
+    }
+
+    pub fn background_color(&self) -> Color32 {
+        match self {
+            ThemeKind::Dark => colors::GENERAL_BACKGROUND_COLOR,
+            ThemeKind::Light => colors::LIGHT_GENERAL_BACKGROUND_COLOR,
+        }
+    }
+
+    pub fn side_panel_color(&self) -> Color32 {
+        match self {
+            ThemeKind::Dark => colors::BUTTON_MENU_BACKGROUND_COLOR,
+            ThemeKind::Light => colors::LIGHT_BUTTON_MENU_BACKGROUND_COLOR,
+        }
+    }
+}
 
 pub struct Theme {
     visuals: Visuals,
 }
 
 impl Theme {
-    pub fn new() -> Self {
-        let mut visuals = Visuals::dark();
+    pub fn new(kind: ThemeKind) -> Self {
+        let mut visuals = match kind {
+            ThemeKind::Dark => Visuals::dark(),
+            ThemeKind::Light => Visuals::light(),
+        };
 
-        visuals.widgets.noninteractive.bg_fill = colors::GENERAL_BACKGROUND_COLOR;
-        visuals.widgets.noninteractive.fg_stroke.color = Color32::WHITE;
+        visuals.widgets.noninteractive.bg_fill = kind.background_color();
         visuals.widgets.noninteractive.corner_radius = 0.;
 
-        visuals.widgets.inactive.bg_fill = colors::BUTTON_BACKGROUND_COLOR;
-        visuals.widgets.hovered.bg_fill = colors::BUTTON_HOVERED_BACKGROUND_COLOR;
-        visuals.widgets.active.bg_fill = colors::BUTTON_ACTIVE_BACKGROUND_COLOR;
+        match kind {
+            ThemeKind::Dark => {
+                visuals.widgets.noninteractive.fg_stroke.color = Color32::WHITE;
+                visuals.widgets.inactive.bg_fill = colors::BUTTON_BACKGROUND_COLOR;
+                visuals.widgets.hovered.bg_fill = colors::BUTTON_HOVERED_BACKGROUND_COLOR;
+                visuals.widgets.active.bg_fill = colors::BUTTON_ACTIVE_BACKGROUND_COLOR;
+            }
+            ThemeKind::Light => {
+                visuals.widgets.noninteractive.fg_stroke.color = Color32::BLACK;
+                visuals.widgets.inactive.bg_fill = colors::LIGHT_BUTTON_BACKGROUND_COLOR;
+                visuals.widgets.hovered.bg_fill = colors::LIGHT_BUTTON_HOVERED_BACKGROUND_COLOR;
+                visuals.widgets.active.bg_fill = colors::LIGHT_BUTTON_ACTIVE_BACKGROUND_COLOR;
+            }
+        }
 
         Self { visuals }
     }
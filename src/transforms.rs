@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::text::graphemes;
+use crate::word_packs::PackWord;
+
+const LEFT_HAND_LETTERS: &str = "qwertasdfgzxcvb";
+
+// Each keyboard row read left to right and right to left
+const KEYBOARD_ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Transform {
+    RandomCase,
+    Reversed,
+    MirroredLayout,
+    LeftHandOnly,
+    DoubleLetters,
+}
+
+pub const ALL_TRANSFORMS: [Transform; 5] = [
+    Transform::RandomCase,
+    Transform::Reversed,
+    Transform::MirroredLayout,
+    Transform::LeftHandOnly,
+    Transform::DoubleLetters,
+];
+
+impl Transform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::RandomCase => "Random Case",
+            Transform::Reversed => "Reversed",
+            Transform::MirroredLayout => "Mirrored Layout",
+            Transform::LeftHandOnly => "Left Hand Only",
+            Transform::DoubleLetters => "Double Letters",
+        }
+    }
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Transform::RandomCase => "random-case",
+            Transform::Reversed => "reversed",
+            Transform::MirroredLayout => "mirrored-layout",
+            Transform::LeftHandOnly => "left-hand-only",
+            Transform::DoubleLetters => "double-letters",
+        }
+    }
+
+    /// Left hand only does nothing, and isn't tagged, for a pack without any left hand words
+    pub fn applies_to(&self, pack_words: &[PackWord]) -> bool {
+        match self {
+            Transform::LeftHandOnly => pack_words.iter().any(|word| is_left_hand_word(&word.text)),
+            _ => true,
+        }
+    }
+
+    pub fn apply(&self, words: Vec<String>, rng: &mut StdRng) -> Vec<String> {
+        match self {
+            Transform::RandomCase => words.iter().map(|word| random_case(word, rng)).collect(),
+            Transform::Reversed => words.iter().map(|word| reverse(word)).collect(),
+            Transform::MirroredLayout => words.iter().map(|word| mirror_layout(word)).collect(),
+            // The words were already picked from the left hand words of the pack
+            Transform::LeftHandOnly => words,
+            Transform::DoubleLetters => words.iter().map(|word| double_letters(word)).collect(),
+        }
+    }
+}
+
+/// The pack words a test picks from, which left hand only narrows down before picking
+pub fn pipeline_pack_words<'a>(
+    pipeline: &[Transform],
+    pack_words: &'a [PackWord],
+) -> Cow<'a, [PackWord]> {
+    if pipeline.contains(&Transform::LeftHandOnly) && Transform::LeftHandOnly.applies_to(pack_words)
+    {
+        Cow::Owned(
+            pack_words
+                .iter()
+                .filter(|word| is_left_hand_word(&word.text))
+                .cloned()
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(pack_words)
+    }
+}
+
+/// The transforms run one after the other, in the order they were added
+pub fn apply_pipeline(pipeline: &[Transform], words: Vec<String>, rng: &mut StdRng) -> Vec<String> {
+    pipeline
+        .iter()
+        .fold(words, |words, transform| transform.apply(words, rng))
+}
+
+fn random_case(word: &str, rng: &mut StdRng) -> String {
+    graphemes(word)
+        .into_iter()
+        .map(|letter| {
+            if rng.gen_bool(0.5) {
+                letter.to_uppercase()
+            } else {
+                letter.to_lowercase()
+            }
+        })
+        .collect()
+}
+
+// Reversing graphemes keeps combining marks on the letter they belong to
+fn reverse(word: &str) -> String {
+    graphemes(word).into_iter().rev().collect()
+}
+
+/// Swaps every letter with the key in the same spot on the other half of the keyboard
+fn mirror_layout(word: &str) -> String {
+    word.chars()
+        .map(|c| {
+            for row in KEYBOARD_ROWS {
+                if let Some(position) = row.find(c) {
+                    return row.as_bytes()[row.len() - 1 - position] as char;
+                }
+            }
+            c
+        })
+        .collect()
+}
+
+fn is_left_hand_word(word: &str) -> bool {
+    word.chars().all(|c| LEFT_HAND_LETTERS.contains(c))
+}
+
+fn double_letters(word: &str) -> String {
+    graphemes(word)
+        .into_iter()
+        .flat_map(|letter| [letter, letter])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_words(words: &[&str]) -> Vec<PackWord> {
+        words
+            .iter()
+            .map(|word| PackWord {
+                text: word.to_string(),
+                difficulty: None,
+                frequency: 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn left_hand_only_picks_from_the_left_hand_words_of_the_pack() {
+        let words = pack_words(&["the", "water", "card", "you", "bed"]);
+
+        let picked = pipeline_pack_words(&[Transform::LeftHandOnly], &words);
+        let texts: Vec<&str> = picked.iter().map(|word| word.text.as_str()).collect();
+
+        assert!(Transform::LeftHandOnly.applies_to(&words));
+        assert_eq!(texts, ["water", "card", "bed"]);
+    }
+
+    #[test]
+    fn left_hand_only_keeps_the_whole_pack_when_it_has_no_left_hand_words() {
+        let words = pack_words(&["you", "him", "look"]);
+
+        let picked = pipeline_pack_words(&[Transform::LeftHandOnly], &words);
+
+        assert!(!Transform::LeftHandOnly.applies_to(&words));
+        assert_eq!(picked.len(), words.len());
+    }
+}
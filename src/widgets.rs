@@ -1,9 +1,10 @@
 use bevy_egui::egui::{
-    self, Button, CentralPanel, Frame, Pos2, Response, RichText, SidePanel, Stroke, Ui, Vec2,
-    Widget, Window,
+    self, Button, CentralPanel, ComboBox, Frame, Pos2, Response, RichText, SidePanel, Stroke, Ui,
+    Vec2, Widget, Window,
 };
 
 use crate::colors;
+use crate::theme::ThemeKind;
 
 const SIDE_PANEL_DEFAULT_WIDTH: f32 = 200.;
 const SIDE_PANEL_TOP_MARGIN: f32 = 200.;
@@ -15,6 +16,7 @@ const CENTRAL_PANEL_CONTEXT_HEIGHT: f32 = 100.;
 const BUTTON_WIDTH: f32 = SIDE_PANEL_DEFAULT_WIDTH;
 const BUTTON_HEIGHT: f32 = SIDE_PANEL_DEFAULT_WIDTH / 4.;
 const BUTTON_STROKE_WIDTH: f32 = 3.;
+const BUTTON_FOCUSED_STROKE_WIDTH: f32 = 5.;
 const BUTTON_SPACE_BETWEEN: f32 = 10.;
 
 pub struct InputField {
@@ -24,11 +26,21 @@ pub struct InputField {
 
 pub struct StyledButton {
     text: String,
+    focused: bool,
 }
 
 impl StyledButton {
     pub fn new(text: impl Into<String>) -> Self {
-        Self { text: text.into() }
+        Self {
+            text: text.into(),
+            focused: false,
+        }
+    }
+
+    /// Outlines the button that keyboard navigation is on
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
     }
 }
 
@@ -36,42 +48,74 @@ impl Widget for StyledButton {
     fn ui(self, ui: &mut Ui) -> Response {
         ui.add_space(BUTTON_SPACE_BETWEEN);
 
+        let stroke = if self.focused {
+            Stroke::new(
+                BUTTON_FOCUSED_STROKE_WIDTH,
+                colors::BUTTON_FOCUSED_STROKE_COLOR,
+            )
+        } else {
+            Stroke::new(BUTTON_STROKE_WIDTH, colors::BUTTON_STROKE_COLOR)
+        };
+
         ui.add_sized(
             [BUTTON_WIDTH, BUTTON_HEIGHT],
-            Button::new(RichText::new(&self.text)).stroke(Stroke::new(
-                BUTTON_STROKE_WIDTH,
-                colors::BUTTON_STROKE_COLOR,
-            )),
+            Button::new(RichText::new(&self.text)).stroke(stroke),
         )
     }
 }
 
-pub struct WindowForLabels;
+pub struct StyledComboBox {
+    combo_box: ComboBox,
+}
+
+impl StyledComboBox {
+    pub fn new(id_source: &str, selected_text: impl ToString) -> Self {
+        Self {
+            combo_box: ComboBox::from_id_source(id_source)
+                .width(BUTTON_WIDTH)
+                .selected_text(selected_text.to_string()),
+        }
+    }
+
+    pub fn combo_box(self) -> ComboBox {
+        self.combo_box
+    }
+}
+
+pub struct WindowForLabels {
+    window: Window<'static>,
+}
 
 impl WindowForLabels {
-    pub fn new(window_width: f32, mut xpos: f32, ypos: f32) -> Window<'static> {
+    pub fn new(window_width: f32, mut xpos: f32, ypos: f32) -> Self {
         // To avoid the below calculation for the window that is only there for getting widths
         if xpos == 0. {
             xpos = (window_width / 2.) + SIDE_PANEL_DEFAULT_WIDTH + SIDE_PANEL_SIDE_MARGIN
                 - (CENTRAL_PANEL_CONTEXT_WIDTH / 2.);
         }
-        Window::new("")
-            .id(egui::Id::new("window_for_labels"))
-            .resizable(false)
-            .collapsible(false)
-            .title_bar(false)
-            .enabled(false)
-            .default_size(Vec2::new(
-                CENTRAL_PANEL_CONTEXT_WIDTH,
-                CENTRAL_PANEL_CONTEXT_HEIGHT,
-            ))
-            .frame(Frame {
-                margin: Vec2::new(0., 0.),
-                stroke: Stroke::new(3., colors::BUTTON_STROKE_COLOR),
-                fill: colors::BUTTON_BACKGROUND_COLOR,
-                ..Default::default()
-            })
-            .current_pos(Pos2::new(xpos, ypos))
+        Self {
+            window: Window::new("")
+                .id(egui::Id::new("window_for_labels"))
+                .resizable(false)
+                .collapsible(false)
+                .title_bar(false)
+                .enabled(false)
+                .default_size(Vec2::new(
+                    CENTRAL_PANEL_CONTEXT_WIDTH,
+                    CENTRAL_PANEL_CONTEXT_HEIGHT,
+                ))
+                .frame(Frame {
+                    margin: Vec2::new(0., 0.),
+                    stroke: Stroke::new(3., colors::BUTTON_STROKE_COLOR),
+                    fill: colors::BUTTON_BACKGROUND_COLOR,
+                    ..Default::default()
+                })
+                .current_pos(Pos2::new(xpos, ypos)),
+        }
+    }
+
+    pub fn window(self) -> Window<'static> {
+        self.window
     }
 }
 
@@ -80,14 +124,14 @@ pub struct StyledSidePanel {
 }
 
 impl StyledSidePanel {
-    pub fn new() -> Self {
+    pub fn new(theme: ThemeKind) -> Self {
         Self {
             panel: SidePanel::left("left_panel")
                 .default_width(SIDE_PANEL_DEFAULT_WIDTH)
                 .resizable(false)
                 .frame(Frame {
                     margin: Vec2::new(SIDE_PANEL_SIDE_MARGIN, SIDE_PANEL_TOP_MARGIN),
-                    fill: colors::BUTTON_MENU_BACKGROUND_COLOR,
+                    fill: theme.side_panel_color(),
                     ..Default::default()
                 }),
         }
@@ -103,14 +147,14 @@ pub struct StyledCentralPanel {
 }
 
 impl StyledCentralPanel {
-    pub fn new(window_width: f32) -> Self {
+    pub fn new(window_width: f32, theme: ThemeKind) -> Self {
         Self {
             panel: CentralPanel::default().frame(Frame {
                 margin: Vec2::new(
                     (window_width - SIDE_PANEL_DEFAULT_WIDTH - CENTRAL_PANEL_CONTEXT_WIDTH) / 2.,
                     CENTRAL_PANEL_CONTEXT_HEIGHT,
                 ),
-                fill: colors::GENERAL_BACKGROUND_COLOR,
+                fill: theme.background_color(),
                 ..Default::default()
             }),
         }
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+use crate::text::{grapheme_count, graphemes};
+use crate::word_packs::PackWord;
+
+pub const MIN_WORD_LENGTH: usize = 1;
+/// A maximum of this many letters means there is no limit
+pub const MAX_WORD_LENGTH: usize = 20;
+
+/// Limits which words of a pack can show up in a random word test
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WordFilters {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Difficulty tags of the pack files to take words from; empty means every file
+    pub difficulties: Vec<String>,
+    /// Words must contain at least one of these letters
+    pub include_letters: String,
+    /// Words can't contain any of these letters
+    pub exclude_letters: String,
+}
+
+impl Default for WordFilters {
+    fn default() -> Self {
+        Self {
+            min_length: MIN_WORD_LENGTH,
+            max_length: MAX_WORD_LENGTH,
+            difficulties: Vec::new(),
+            include_letters: String::new(),
+            exclude_letters: String::new(),
+        }
+    }
+}
+
+/// The filters with their letter sets worked out once, to check a whole pack with
+pub struct WordMatcher<'a> {
+    filters: &'a WordFilters,
+    include_letters: Vec<String>,
+    exclude_letters: Vec<String>,
+}
+
+impl WordMatcher<'_> {
+    pub fn matches(&self, word: &PackWord) -> bool {
+        let length = grapheme_count(&word.text);
+
+        if length < self.filters.min_length
+            || (self.filters.max_length < MAX_WORD_LENGTH && length > self.filters.max_length)
+        {
+            return false;
+        }
+
+        if !self.filters.difficulties.is_empty() {
+            match &word.difficulty {
+                Some(difficulty) if self.filters.difficulties.contains(difficulty) => {}
+                _ => return false,
+            }
+        }
+
+        let letters: Vec<String> = graphemes(&word.text)
+            .into_iter()
+            .map(|letter| letter.to_lowercase())
+            .collect();
+
+        if !self.include_letters.is_empty()
+            && !letters
+                .iter()
+                .any(|letter| self.include_letters.contains(letter))
+        {
+            return false;
+        }
+
+        !letters
+            .iter()
+            .any(|letter| self.exclude_letters.contains(letter))
+    }
+}
+
+impl WordFilters {
+    pub fn matcher(&self) -> WordMatcher<'_> {
+        WordMatcher {
+            filters: self,
+            include_letters: letter_set(&self.include_letters),
+            exclude_letters: letter_set(&self.exclude_letters),
+        }
+    }
+
+    /// When no word of the pack is left, random word tests ignore the filters
+    pub fn apply_to(&self, words: &[PackWord]) -> bool {
+        let matcher = self.matcher();
+        words.iter().any(|word| matcher.matches(word))
+    }
+
+    /// Stored with a result so filtered runs are only compared with runs using the same filters
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+
+        if self.min_length > MIN_WORD_LENGTH || self.max_length < MAX_WORD_LENGTH {
+            tags.push(format!(
+                "length:{}-{}",
+                self.min_length,
+                self.max_length_text()
+            ));
+        }
+
+        let mut difficulties = self.difficulties.clone();
+        difficulties.sort();
+        for difficulty in difficulties {
+            tags.push(format!("difficulty:{}", difficulty));
+        }
+
+        let include_letters: String = letter_set(&self.include_letters).concat();
+        if !include_letters.is_empty() {
+            tags.push(format!("include:{}", include_letters));
+        }
+
+        let exclude_letters: String = letter_set(&self.exclude_letters).concat();
+        if !exclude_letters.is_empty() {
+            tags.push(format!("exclude:{}", exclude_letters));
+        }
+        tags
+    }
+
+    pub fn max_length_text(&self) -> String {
+        if self.max_length >= MAX_WORD_LENGTH {
+            "any".to_string()
+        } else {
+            self.max_length.to_string()
+        }
+    }
+}
+
+// Letters are compared lowercased and whitespace or commas between them are ignored
+fn letter_set(letters: &str) -> Vec<String> {
+    let mut set: Vec<String> = graphemes(letters)
+        .into_iter()
+        .filter(|letter| !letter.trim().is_empty() && *letter != ",")
+        .map(|letter| letter.to_lowercase())
+        .collect();
+    set.sort();
+    set.dedup();
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, difficulty: Option<&str>) -> PackWord {
+        PackWord {
+            text: text.to_string(),
+            difficulty: difficulty.map(|difficulty| difficulty.to_string()),
+            frequency: 1,
+        }
+    }
+
+    #[test]
+    fn filters_on_length_in_letters() {
+        let filters = WordFilters {
+            min_length: 2,
+            max_length: 3,
+            ..Default::default()
+        };
+        let matcher = filters.matcher();
+
+        assert!(!matcher.matches(&word("a", None)));
+        assert!(matcher.matches(&word("to", None)));
+        // Three letters, however many bytes they take
+        assert!(matcher.matches(&word("für", None)));
+        assert!(!matcher.matches(&word("word", None)));
+    }
+
+    #[test]
+    fn filters_on_letters_ignoring_case() {
+        let filters = WordFilters {
+            include_letters: "Q, z".to_string(),
+            exclude_letters: "u".to_string(),
+            ..Default::default()
+        };
+        let matcher = filters.matcher();
+
+        assert!(matcher.matches(&word("Zero", None)));
+        assert!(!matcher.matches(&word("quiz", None)));
+        assert!(!matcher.matches(&word("word", None)));
+    }
+
+    #[test]
+    fn filters_on_difficulty() {
+        let filters = WordFilters {
+            difficulties: vec!["easy".to_string()],
+            ..Default::default()
+        };
+        let matcher = filters.matcher();
+
+        assert!(matcher.matches(&word("the", Some("easy"))));
+        assert!(!matcher.matches(&word("rhythm", Some("hard"))));
+        assert!(!matcher.matches(&word("untagged", None)));
+    }
+
+    #[test]
+    fn tags_describe_the_active_filters() {
+        assert!(WordFilters::default().tags().is_empty());
+
+        let filters = WordFilters {
+            min_length: 3,
+            difficulties: vec!["hard".to_string(), "easy".to_string()],
+            include_letters: "b, a".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.tags(),
+            vec![
+                "length:3-any",
+                "difficulty:easy",
+                "difficulty:hard",
+                "include:ab"
+            ]
+        );
+    }
+}
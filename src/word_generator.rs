@@ -1,21 +1,60 @@
-use rand::Rng;
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::seq::index;
+use std::time::{Duration, Instant};
 
-const WORD_COUNT_PER_GAME: i32 = 132; //Should be divisible by 3
+use crate::replay::Keystroke;
+use crate::word_filters::WordFilters;
+use crate::word_packs::PackWord;
 
-pub struct AllWords {
-    pub all_words: Vec<String>,
-}
+pub const WORD_COUNT_PER_GAME: usize = 132; //Should be divisible by 3
 
-impl AllWords {
-    pub fn new() -> Self {
-        Self {
-            all_words: load_words(),
-        }
-    }
-}
+// At most one in this many words of a random word test is a review word
+const REVIEW_SHARE_DIVISOR: usize = 5;
+
+// {a} and {b} become identifiers, {T} a type name and {n} a digit
+const SYMBOL_DRILLS: [&str; 36] = [
+    "{a}->{b}",
+    "fn()->{T}",
+    "{a}=>{b}",
+    "{T}::new()",
+    "{a}::{b}",
+    "{a}&&{b}",
+    "{a}||{b}",
+    "{a}!={b}",
+    "{a}=={b}",
+    "{a}<={n}",
+    "{a}>={n}",
+    "{a}+={n}",
+    "{a}-={n}",
+    "#[derive({T})]",
+    "#[test]",
+    "&mut",
+    "&{a}",
+    "*{a}",
+    "!{a}",
+    "{a}?;",
+    "{a}[{n}]",
+    "{a}.{b}()",
+    "({a},{b})",
+    "{{a}}",
+    "|{a}|",
+    "Vec<{T}>",
+    "Option<&{T}>",
+    "'{a}'",
+    "\"{a}\"",
+    "{a}..{n}",
+    "{a}%{n}",
+    "{a}^{b}",
+    "//",
+    "/*{a}*/",
+    "@{a}",
+    "${a}",
+];
+const SYMBOL_DRILL_IDENTIFIERS: [&str; 12] = [
+    "x", "y", "i", "self", "vec", "map", "ok", "err", "node", "data", "key", "val",
+];
+const SYMBOL_DRILL_TYPES: [&str; 6] = ["T", "Vec", "String", "Debug", "Clone", "Option"];
 
 pub struct WordListIndex {
     pub current_index: usize,
@@ -23,58 +62,320 @@ pub struct WordListIndex {
 
 pub struct WordList {
     pub list: Vec<String>,
+    /// The same seed and settings always give the same words
+    pub seed: u64,
+    /// Where the words came from when it wasn't the player's settings, such as a ghost
+    pub tag: Option<String>,
+    /// The pack the words are from when it isn't the one picked in the settings, as in a race
+    pub pack: Option<String>,
 }
 
-impl WordList {
-    pub fn new(word_list: Vec<String>) -> Self {
-        Self {
-            list: get_random_word_list(word_list),
-        }
+/// Words due for review take the place of some of the random words, the longest overdue
+/// first as `due_words` is ordered
+pub fn get_pack_word_list(
+    word_list: &[PackWord],
+    filters: &WordFilters,
+    due_words: &[String],
+    rng: &mut StdRng,
+) -> Vec<String> {
+    let mut list = get_random_word_list(word_list, filters, rng);
+
+    let matcher = filters.matcher();
+    let review_words: Vec<&PackWord> = due_words
+        .iter()
+        .filter_map(|due_word| word_list.iter().find(|word| &word.text == due_word))
+        .filter(|word| matcher.matches(word))
+        .collect();
+    let review_count = review_words.len().min(list.len() / REVIEW_SHARE_DIVISOR);
+
+    for (position, review_word) in index::sample(rng, list.len(), review_count)
+        .into_iter()
+        .zip(review_words)
+    {
+        list[position] = review_word.text.clone();
     }
+
+    list
 }
 
 pub struct PlayerWordList {
     pub list: Vec<String>,
+    /// How long each word took, from the previous word (or the first keystroke) to its space
+    pub word_seconds: Vec<f32>,
+    /// Every change to the input field, for replays
+    pub keystrokes: Vec<Keystroke>,
+    word_started: Option<Instant>,
+    paused_at: Option<Instant>,
 }
 
 impl PlayerWordList {
     pub fn new() -> Self {
-        Self { list: Vec::new() }
+        Self {
+            list: Vec::new(),
+            word_seconds: Vec::new(),
+            keystrokes: Vec::new(),
+            word_started: None,
+            paused_at: None,
+        }
+    }
+
+    pub fn start_timing(&mut self) {
+        self.word_started = Some(Instant::now());
+    }
+
+    pub fn pause_timing(&mut self) {
+        self.paused_at = Some(Instant::now());
+    }
+
+    /// The time spent paused doesn't count towards the current word
+    pub fn resume_timing(&mut self) {
+        if let (Some(paused_at), Some(word_started)) = (self.paused_at.take(), self.word_started) {
+            self.word_started = Some(word_started + paused_at.elapsed());
+        }
+    }
+
+    /// Seconds since the first keystroke, leaving out the time spent paused
+    pub fn elapsed_seconds(&self) -> f32 {
+        let current_word_seconds = match (self.word_started, self.paused_at) {
+            (Some(word_started), Some(paused_at)) => paused_at
+                .saturating_duration_since(word_started)
+                .as_secs_f32(),
+            (Some(word_started), None) => word_started.elapsed().as_secs_f32(),
+            (None, _) => 0.,
+        };
+
+        self.word_seconds.iter().sum::<f32>() + current_word_seconds
+    }
+
+    pub fn record_keystroke(&mut self, word: usize, input: &str) {
+        let seconds = self.elapsed_seconds();
+
+        self.keystrokes.push(Keystroke {
+            seconds,
+            word,
+            input: input.to_string(),
+        });
+    }
+
+    pub fn push_word(&mut self, word: String) {
+        let seconds = self
+            .word_started
+            .map(|started| started.elapsed().as_secs_f32())
+            .unwrap_or(0.);
+
+        self.list.push(word);
+        self.word_seconds.push(seconds);
+        self.start_timing();
+    }
+
+    /// Going back to a word keeps counting the time already spent on it
+    pub fn pop_word(&mut self) -> Option<String> {
+        let seconds = self.word_seconds.pop().unwrap_or(0.);
+        self.word_started = Some(Instant::now() - Duration::from_secs_f32(seconds));
+        self.list.pop()
     }
 }
 
-fn get_random_word_list(word_list: Vec<String>) -> Vec<String> {
-    let total_word_count: usize = word_list.len();
+/// Common words come up more often when the pack lists word frequencies
+fn get_random_word_list(
+    all_words: &[PackWord],
+    filters: &WordFilters,
+    rng: &mut StdRng,
+) -> Vec<String> {
     let mut words_for_game: Vec<String> = Vec::new();
-    let mut rng = rand::thread_rng();
+
+    // Better to ignore the filters than to end up with an empty test; the result isn't tagged
+    // with them either
+    let word_list: Vec<&PackWord> = if filters.apply_to(all_words) {
+        let matcher = filters.matcher();
+        all_words
+            .iter()
+            .filter(|word| matcher.matches(word))
+            .collect()
+    } else {
+        all_words.iter().collect()
+    };
+
+    let weights = match WeightedIndex::new(word_list.iter().map(|word| word.frequency)) {
+        Ok(weights) => weights,
+        Err(_) => return words_for_game,
+    };
 
     for _ in 0..WORD_COUNT_PER_GAME {
-        words_for_game.push(word_list[rng.gen_range(1..total_word_count)].clone());
+        words_for_game.push(word_list[weights.sample(rng)].text.clone());
     }
 
     words_for_game
 }
 
-pub fn load_words() -> Vec<String> {
-    let mut all_words = Vec::new();
+/// Digit groups, phone numbers, prices and dates for practicing the number row and numpad
+pub fn get_number_drill_list(rng: &mut StdRng) -> Vec<String> {
+    let mut words_for_game: Vec<String> = Vec::new();
 
-    if let Ok(lines) = read_lines("words.txt") {
-        // Consumes the iterator, returns an (Optional) String
-        for line in lines {
-            if let Ok(word) = line {
-                all_words.push(word);
+    for _ in 0..WORD_COUNT_PER_GAME {
+        let drill = match rng.gen_range(0..4) {
+            0 => {
+                let length = rng.gen_range(2..=6);
+                random_digits(length, rng)
             }
-        }
-    } else if let Err(_) = read_lines("words.txt") {
-        panic!("The words file could not be read; closing application")
+            1 => format!(
+                "{}-{}-{}",
+                random_digits(3, rng),
+                random_digits(3, rng),
+                random_digits(4, rng)
+            ),
+            2 => format!("${}.{:02}", rng.gen_range(1..1000), rng.gen_range(0..100)),
+            _ => format!(
+                "{:04}-{:02}-{:02}",
+                rng.gen_range(1950..2050),
+                rng.gen_range(1..=12),
+                rng.gen_range(1..=28)
+            ),
+        };
+        words_for_game.push(drill);
+    }
+
+    words_for_game
+}
+
+fn random_digits(length: usize, rng: &mut StdRng) -> String {
+    (0..length)
+        .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+        .collect()
+}
+
+/// Brackets, operators and common code tokens the word lists never contain
+pub fn get_symbol_drill_list(rng: &mut StdRng) -> Vec<String> {
+    let mut words_for_game: Vec<String> = Vec::new();
+
+    for _ in 0..WORD_COUNT_PER_GAME {
+        let drill = SYMBOL_DRILLS[rng.gen_range(0..SYMBOL_DRILLS.len())]
+            .replace(
+                "{a}",
+                SYMBOL_DRILL_IDENTIFIERS[rng.gen_range(0..SYMBOL_DRILL_IDENTIFIERS.len())],
+            )
+            .replace(
+                "{b}",
+                SYMBOL_DRILL_IDENTIFIERS[rng.gen_range(0..SYMBOL_DRILL_IDENTIFIERS.len())],
+            )
+            .replace(
+                "{T}",
+                SYMBOL_DRILL_TYPES[rng.gen_range(0..SYMBOL_DRILL_TYPES.len())],
+            )
+            .replace("{n}", &rng.gen_range(0..10).to_string());
+
+        words_for_game.push(drill);
     }
-    all_words
+
+    words_for_game
 }
 
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_words(words: &[&str]) -> Vec<PackWord> {
+        words
+            .iter()
+            .map(|word| PackWord {
+                text: word.to_string(),
+                difficulty: None,
+                frequency: 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_words() {
+        let words = pack_words(&["the", "of", "and", "to", "a", "in", "is", "you"]);
+        let filters = WordFilters::default();
+        let due_words = vec!["you".to_string()];
+
+        let first = get_pack_word_list(&words, &filters, &due_words, &mut StdRng::seed_from_u64(7));
+        let second =
+            get_pack_word_list(&words, &filters, &due_words, &mut StdRng::seed_from_u64(7));
+        let other = get_pack_word_list(&words, &filters, &due_words, &mut StdRng::seed_from_u64(8));
+
+        assert_eq!(first.len(), WORD_COUNT_PER_GAME);
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn only_uses_words_the_filters_let_through() {
+        let words = pack_words(&["a", "to", "the", "word", "words"]);
+        let filters = WordFilters {
+            min_length: 3,
+            max_length: 4,
+            ..Default::default()
+        };
+
+        let list = get_pack_word_list(&words, &filters, &[], &mut StdRng::seed_from_u64(1));
+        assert!(list.iter().all(|word| word == "the" || word == "word"));
+    }
+
+    #[test]
+    fn ignores_filters_that_leave_no_words() {
+        let words = pack_words(&["the", "of"]);
+        let filters = WordFilters {
+            include_letters: "z".to_string(),
+            ..Default::default()
+        };
+
+        let list = get_pack_word_list(&words, &filters, &[], &mut StdRng::seed_from_u64(1));
+        assert_eq!(list.len(), WORD_COUNT_PER_GAME);
+        assert!(!filters.apply_to(&words));
+    }
+
+    #[test]
+    fn mixes_in_a_share_of_the_due_words() {
+        let words = pack_words(&["the", "of", "rhythm"]);
+        let due_words = vec!["rhythm".to_string(), "missing".to_string()];
+        let filters = WordFilters {
+            exclude_letters: "y".to_string(),
+            ..Default::default()
+        };
+
+        // A due word the filters leave out stays out
+        let list = get_pack_word_list(&words, &filters, &due_words, &mut StdRng::seed_from_u64(3));
+        assert!(!list.contains(&"rhythm".to_string()));
+
+        let list = get_pack_word_list(
+            &words,
+            &WordFilters::default(),
+            &due_words,
+            &mut StdRng::seed_from_u64(3),
+        );
+        assert!(list.contains(&"rhythm".to_string()));
+        assert!(!list.contains(&"missing".to_string()));
+    }
+
+    #[test]
+    fn takes_the_longest_overdue_words_when_more_are_due_than_fit() {
+        let mut words = pack_words(&["the"]);
+        let due_words: Vec<String> = (0..40).rev().map(|n| format!("review{}", n)).collect();
+        // Weighted 0 so only the review share brings them in, listed in the pack oldest last
+        words.extend((0..40).map(|n| PackWord {
+            text: format!("review{}", n),
+            difficulty: None,
+            frequency: 0,
+        }));
+
+        let list = get_pack_word_list(
+            &words,
+            &WordFilters::default(),
+            &due_words,
+            &mut StdRng::seed_from_u64(5),
+        );
+        let review_count = WORD_COUNT_PER_GAME / REVIEW_SHARE_DIVISOR;
+
+        for (position, due_word) in due_words.iter().enumerate() {
+            assert_eq!(
+                list.contains(due_word),
+                position < review_count,
+                "{}",
+                due_word
+            );
+        }
+    }
 }
@@ -0,0 +1,510 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::path::{Component, Path, PathBuf};
+
+use crate::text::visual_order;
+
+const PACKS_DIRECTORY: &str = "packs";
+const PACK_MANIFEST_FILE: &str = "pack.json";
+pub const DEFAULT_PACK: &str = "english";
+/// Word weights are summed as a u32 when picking words, so bigger counts are scaled down
+const MAX_TOTAL_FREQUENCY: u64 = u32::MAX as u64 / 2;
+
+/// Also a resource holding the direction of the words in the current test
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// The `pack.json` file every pack folder must contain
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackManifest {
+    name: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    license: Option<String>,
+    /// Where the words were taken from
+    #[serde(default)]
+    source: Option<String>,
+    language: String,
+    script: String,
+    direction: TextDirection,
+    /// A file of `word count` lines; words without a count are weighted as 1
+    #[serde(default)]
+    frequencies: Option<String>,
+    /// A font with the glyphs of the pack's script, for scripts the built-in fonts lack
+    #[serde(default)]
+    font: Option<String>,
+    files: Vec<PackFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    path: String,
+    #[serde(default)]
+    difficulty: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct PackWord {
+    pub text: String,
+    pub difficulty: Option<String>,
+    pub frequency: u32,
+}
+
+pub struct WordPack {
+    pub id: String,
+    pub name: String,
+    pub author: Option<String>,
+    pub license: Option<String>,
+    pub source: Option<String>,
+    pub language: String,
+    pub script: String,
+    pub direction: TextDirection,
+    pub font: Option<Vec<u8>>,
+    pub words: Vec<PackWord>,
+}
+
+impl WordPack {
+    /// The difficulty tags of the included files, in the order they were listed
+    pub fn difficulty_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+
+        for word in &self.words {
+            if let Some(difficulty) = &word.difficulty {
+                if !tags.contains(difficulty) {
+                    tags.push(difficulty.clone());
+                }
+            }
+        }
+        tags
+    }
+
+    /// The name as it should be drawn, native names of right to left languages included
+    pub fn display_name(&self) -> String {
+        visual_order(&self.name, self.direction)
+    }
+
+    pub fn description(&self) -> String {
+        let mut description = format!(
+            "{} ({}, {} script, {} words) by {}, license: {}",
+            self.display_name(),
+            self.language,
+            self.script,
+            self.words.len(),
+            self.author.as_deref().unwrap_or("unknown"),
+            self.license.as_deref().unwrap_or("unknown")
+        );
+        if let Some(source) = &self.source {
+            description.push_str(&format!(", source: {}", source));
+        }
+        description
+    }
+}
+
+/// Every valid word pack found in the packs folder; the folder name is the pack id
+pub struct WordPacks {
+    pub packs: Vec<WordPack>,
+    pub errors: Vec<String>,
+}
+
+impl WordPacks {
+    pub fn load() -> Self {
+        let mut packs = Vec::new();
+        let mut errors = Vec::new();
+
+        match fs::read_dir(PACKS_DIRECTORY) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    if !entry.path().is_dir() {
+                        continue;
+                    }
+
+                    let id = entry.file_name().to_string_lossy().to_string();
+
+                    match load_pack(&id, &entry.path()) {
+                        Ok(pack) => packs.push(pack),
+                        Err(error) => errors.push(format!("{}: {}", id, error)),
+                    }
+                }
+            }
+            Err(error) => errors.push(format!(
+                "The {} folder could not be read: {}",
+                PACKS_DIRECTORY, error
+            )),
+        }
+
+        if packs.is_empty() && errors.is_empty() {
+            errors.push(format!("No word packs were found in {}", PACKS_DIRECTORY));
+        }
+        packs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self { packs, errors }
+    }
+
+    /// Falls back to the default pack, or the first one, when the chosen pack was removed
+    pub fn get(&self, id: &str) -> Option<&WordPack> {
+        self.packs
+            .iter()
+            .find(|pack| pack.id == id)
+            .or_else(|| self.packs.iter().find(|pack| pack.id == DEFAULT_PACK))
+            .or_else(|| self.packs.first())
+    }
+}
+
+fn load_pack(id: &str, pack_path: &Path) -> Result<WordPack, String> {
+    let manifest_json = fs::read_to_string(pack_path.join(PACK_MANIFEST_FILE))
+        .map_err(|error| format!("{} could not be read: {}", PACK_MANIFEST_FILE, error))?;
+
+    let manifest: PackManifest = serde_json::from_str(&manifest_json)
+        .map_err(|error| format!("{} is not valid: {}", PACK_MANIFEST_FILE, error))?;
+
+    if manifest.name.trim().is_empty() {
+        return Err("the pack needs a name".to_string());
+    }
+    if manifest.language.trim().is_empty() {
+        return Err("the pack needs a language code".to_string());
+    }
+    if manifest.script.trim().is_empty() {
+        return Err("the pack needs a script".to_string());
+    }
+    if manifest.files.is_empty() {
+        return Err("the pack doesn't include any files".to_string());
+    }
+
+    let frequencies = match &manifest.frequencies {
+        Some(frequencies_file) => load_frequencies(&pack_file_path(pack_path, frequencies_file)?)?,
+        None => HashMap::new(),
+    };
+
+    let font = match &manifest.font {
+        Some(font_file) => Some(
+            fs::read(pack_file_path(pack_path, font_file)?)
+                .map_err(|error| format!("{} could not be read: {}", font_file, error))?,
+        ),
+        None => None,
+    };
+
+    let mut words: Vec<PackWord> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for pack_file in &manifest.files {
+        for text in load_words(&pack_file_path(pack_path, &pack_file.path)?)? {
+            // A word listed in several files keeps the first difficulty it was given
+            if !seen.insert(text.clone()) {
+                continue;
+            }
+
+            words.push(PackWord {
+                frequency: frequencies.get(&text).copied().unwrap_or(1),
+                difficulty: pack_file.difficulty.clone(),
+                text,
+            });
+        }
+    }
+
+    if words.is_empty() {
+        return Err("the included files don't contain any words".to_string());
+    }
+    scale_frequencies(&mut words);
+
+    Ok(WordPack {
+        id: id.to_string(),
+        name: manifest.name,
+        author: manifest.author,
+        license: manifest.license,
+        source: manifest.source,
+        language: manifest.language,
+        script: manifest.script,
+        direction: manifest.direction,
+        font,
+        words,
+    })
+}
+
+/// Files named in the manifest have to be inside the pack folder
+fn pack_file_path(pack_path: &Path, file: &str) -> Result<PathBuf, String> {
+    let inside = Path::new(file)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if file.trim().is_empty() || !inside {
+        return Err(format!("{} is not a path inside the pack folder", file));
+    }
+    Ok(pack_path.join(file))
+}
+
+/// Keeps the ratios between the counts while making sure their sum fits in a u32
+fn scale_frequencies(words: &mut [PackWord]) {
+    let total: u64 = words.iter().map(|word| word.frequency as u64).sum();
+    if total <= MAX_TOTAL_FREQUENCY {
+        return;
+    }
+
+    let divisor = total / MAX_TOTAL_FREQUENCY + 1;
+    for word in words {
+        word.frequency = ((word.frequency as u64 / divisor) as u32).max(1);
+    }
+}
+
+fn load_words(path: &Path) -> Result<Vec<String>, String> {
+    let lines = read_lines(path)
+        .map_err(|error| format!("{} could not be read: {}", path.display(), error))?;
+    let mut all_words = Vec::new();
+
+    for (line_number, line) in lines.enumerate() {
+        let line = line
+            .map_err(|error| format!("{} line {}: {}", path.display(), line_number + 1, error))?;
+        let word = line.trim();
+
+        if word.is_empty() {
+            continue;
+        }
+        if word.contains(char::is_whitespace) {
+            return Err(format!(
+                "{} line {}: \"{}\" should be a single word",
+                path.display(),
+                line_number + 1,
+                word
+            ));
+        }
+        all_words.push(word.to_string());
+    }
+    Ok(all_words)
+}
+
+fn load_frequencies(path: &Path) -> Result<HashMap<String, u32>, String> {
+    let lines = read_lines(path)
+        .map_err(|error| format!("{} could not be read: {}", path.display(), error))?;
+    let mut frequencies = HashMap::new();
+
+    for (line_number, line) in lines.enumerate() {
+        let line = line
+            .map_err(|error| format!("{} line {}: {}", path.display(), line_number + 1, error))?;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.as_slice() {
+            [] => continue,
+            [word, count] => match count.parse::<u32>() {
+                Ok(count) if count > 0 => {
+                    frequencies.insert(word.to_string(), count);
+                }
+                _ => {
+                    return Err(format!(
+                        "{} line {}: \"{}\" is not a positive count",
+                        path.display(),
+                        line_number + 1,
+                        count
+                    ))
+                }
+            },
+            _ => {
+                return Err(format!(
+                    "{} line {}: expected a word and a count",
+                    path.display(),
+                    line_number + 1
+                ))
+            }
+        }
+    }
+    Ok(frequencies)
+}
+
+fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(filename)?;
+    Ok(io::BufReader::new(file).lines())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pack folder written to a temp directory, removed when dropped
+    struct TestPack {
+        directory: tempfile::TempDir,
+    }
+
+    impl TestPack {
+        fn new(manifest: &str, files: &[(&str, &str)]) -> Self {
+            let directory = tempfile::tempdir().unwrap();
+            fs::write(directory.path().join(PACK_MANIFEST_FILE), manifest).unwrap();
+            for (name, contents) in files {
+                fs::write(directory.path().join(name), contents).unwrap();
+            }
+            Self { directory }
+        }
+
+        fn load(&self) -> Result<WordPack, String> {
+            load_pack("test", self.directory.path())
+        }
+    }
+
+    fn manifest(files: &str, extra: &str) -> String {
+        format!(
+            r#"{{"name": "Test", "language": "en", "script": "Latin", "direction": "ltr", {} "files": [{}]}}"#,
+            extra, files
+        )
+    }
+
+    #[test]
+    fn loads_words_with_their_difficulty_and_frequency() {
+        let pack = TestPack::new(
+            &manifest(
+                r#"{"path": "easy.txt", "difficulty": "easy"}, {"path": "hard.txt", "difficulty": "hard"}"#,
+                r#""frequencies": "frequencies.txt","#,
+            ),
+            &[
+                ("easy.txt", "the\nof\n\n"),
+                ("hard.txt", "of\nrhythm\n"),
+                ("frequencies.txt", "the 50\nrhythm 2\n"),
+            ],
+        )
+        .load()
+        .unwrap();
+
+        let words: Vec<(&str, Option<&str>, u32)> = pack
+            .words
+            .iter()
+            .map(|word| {
+                (
+                    word.text.as_str(),
+                    word.difficulty.as_deref(),
+                    word.frequency,
+                )
+            })
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                ("the", Some("easy"), 50),
+                // The first file listing a word decides its difficulty
+                ("of", Some("easy"), 1),
+                ("rhythm", Some("hard"), 2),
+            ]
+        );
+        assert_eq!(pack.difficulty_tags(), vec!["easy", "hard"]);
+    }
+
+    #[test]
+    fn rejects_a_manifest_missing_fields() {
+        let pack = TestPack::new(
+            r#"{"name": "", "language": "en", "script": "Latin", "direction": "ltr", "files": [{"path": "words.txt"}]}"#,
+            &[("words.txt", "word\n")],
+        );
+        assert_eq!(pack.load().err().unwrap(), "the pack needs a name");
+
+        let pack = TestPack::new(&manifest("", ""), &[]);
+        assert_eq!(
+            pack.load().err().unwrap(),
+            "the pack doesn't include any files"
+        );
+
+        let pack = TestPack::new(
+            r#"{"name": "Test", "language": "en", "script": "Latin", "direction": "up", "files": []}"#,
+            &[],
+        );
+        assert!(pack.load().err().unwrap().contains("is not valid"));
+    }
+
+    #[test]
+    fn rejects_word_lists_that_are_not_one_word_per_line() {
+        let pack = TestPack::new(
+            &manifest(r#"{"path": "words.txt"}"#, ""),
+            &[("words.txt", "one\ntwo words\n")],
+        );
+        assert!(pack
+            .load()
+            .err()
+            .unwrap()
+            .contains("line 2: \"two words\" should be a single word"));
+
+        let pack = TestPack::new(
+            &manifest(r#"{"path": "words.txt"}"#, ""),
+            &[("words.txt", "\n\n")],
+        );
+        assert_eq!(
+            pack.load().err().unwrap(),
+            "the included files don't contain any words"
+        );
+    }
+
+    #[test]
+    fn rejects_counts_that_are_not_positive() {
+        let pack = TestPack::new(
+            &manifest(
+                r#"{"path": "words.txt"}"#,
+                r#""frequencies": "frequencies.txt","#,
+            ),
+            &[("words.txt", "word\n"), ("frequencies.txt", "word 0\n")],
+        );
+        assert!(pack
+            .load()
+            .err()
+            .unwrap()
+            .contains("\"0\" is not a positive count"));
+    }
+
+    #[test]
+    fn rejects_files_outside_the_pack_folder() {
+        for path in ["../words.txt", "/etc/hostname", "lists/../../words.txt", ""] {
+            let pack = TestPack::new(
+                &manifest(&format!(r#"{{"path": "{}"}}"#, path), ""),
+                &[("words.txt", "word\n")],
+            );
+            assert_eq!(
+                pack.load().err().unwrap(),
+                format!("{} is not a path inside the pack folder", path)
+            );
+        }
+
+        let pack = TestPack::new(
+            &manifest(r#"{"path": "words.txt"}"#, r#""font": "../font.ttf","#),
+            &[("words.txt", "word\n")],
+        );
+        assert!(pack.load().is_err());
+    }
+
+    #[test]
+    fn scales_counts_that_would_overflow() {
+        let pack = TestPack::new(
+            &manifest(
+                r#"{"path": "words.txt"}"#,
+                r#""frequencies": "frequencies.txt","#,
+            ),
+            &[
+                ("words.txt", "the\nof\nrare\n"),
+                ("frequencies.txt", "the 4000000000\nof 2000000000\nrare 1\n"),
+            ],
+        )
+        .load()
+        .unwrap();
+
+        let total: u64 = pack.words.iter().map(|word| word.frequency as u64).sum();
+        assert!(total <= u32::MAX as u64);
+        assert!(pack.words[0].frequency > pack.words[1].frequency);
+        assert_eq!(pack.words[2].frequency, 1);
+    }
+
+    #[test]
+    fn bundled_packs_load_and_name_their_author_and_license() {
+        let packs = WordPacks::load();
+
+        assert!(packs.errors.is_empty(), "{:?}", packs.errors);
+        assert!(packs.packs.iter().any(|pack| pack.id == DEFAULT_PACK));
+        for pack in &packs.packs {
+            assert!(pack.author.is_some(), "{} has no author", pack.id);
+            assert!(pack.license.is_some(), "{} has no license", pack.id);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Runs the race server binary and races two clients through it over TCP
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+#[allow(dead_code)]
+#[path = "../src/race_protocol.rs"]
+mod race_protocol;
+
+use race_protocol::{encode, ClientMessage, ServerMessage};
+
+/// Kills the server when the test ends, whether it passed or not
+struct Server {
+    child: Child,
+    /// Kept open, as the server fails to print once nothing reads what it prints
+    _output: BufReader<ChildStdout>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts the server on a free port and returns the address it printed
+fn start_server() -> (Server, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_race_server"))
+        .arg("127.0.0.1:0")
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the race server should start");
+
+    let mut output = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    output.read_line(&mut line).unwrap();
+    let address = line
+        .trim()
+        .strip_prefix("Race server listening on ")
+        .and_then(|rest| rest.split(' ').next())
+        .expect("the server should print its address")
+        .to_string();
+
+    (
+        Server {
+            child,
+            _output: output,
+        },
+        address,
+    )
+}
+
+struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Client {
+    fn connect(address: &str, name: &str) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        // A message that never comes fails the test instead of hanging it
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut client = Self { stream, reader };
+        client.send(ClientMessage::Join {
+            name: name.to_string(),
+        });
+        client
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        self.stream.write_all(encode(&message).as_bytes()).unwrap();
+    }
+
+    fn receive(&mut self) -> ServerMessage {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    /// Skips messages until one matches, such as lobby updates sent in between
+    fn receive_until(&mut self, matches: impl Fn(&ServerMessage) -> bool) -> ServerMessage {
+        loop {
+            let message = self.receive();
+            if matches(&message) {
+                return message;
+            }
+        }
+    }
+}
+
+#[test]
+fn two_players_race_from_the_lobby_to_the_results() {
+    let (_server, address) = start_server();
+
+    let mut alice = Client::connect(&address, "Alice");
+    alice.receive_until(|message| matches!(message, ServerMessage::Lobby { .. }));
+    let mut bob = Client::connect(&address, "Bob");
+
+    // Both see the full lobby once Bob joined
+    for client in [&mut alice, &mut bob] {
+        let lobby = client.receive_until(
+            |message| matches!(message, ServerMessage::Lobby { players } if players.len() == 2),
+        );
+        if let ServerMessage::Lobby { players } = lobby {
+            let names: Vec<&str> = players.iter().map(|player| player.name.as_str()).collect();
+            assert_eq!(names, ["Alice", "Bob"]);
+            assert!(players.iter().all(|player| !player.ready));
+        }
+    }
+
+    alice.send(ClientMessage::Ready { ready: true });
+    bob.send(ClientMessage::Ready { ready: true });
+
+    // Everyone gets the same seed, so everyone types the same words
+    let seeds: Vec<u64> = [&mut alice, &mut bob]
+        .into_iter()
+        .map(|client| {
+            match client.receive_until(|message| matches!(message, ServerMessage::Start { .. })) {
+                ServerMessage::Start { seed, pack, .. } => {
+                    assert_eq!(pack, "english");
+                    seed
+                }
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    assert_eq!(seeds[0], seeds[1]);
+
+    alice.send(ClientMessage::Progress {
+        words: 5,
+        correct_words: 5,
+    });
+    let standings = bob.receive_until(|message| {
+        matches!(message, ServerMessage::Standings { racers } if racers[0].correct_words == 5)
+    });
+    if let ServerMessage::Standings { racers } = standings {
+        assert_eq!(racers[0].name, "Alice");
+        assert_eq!(racers.len(), 2);
+    }
+
+    alice.send(ClientMessage::Finish {
+        wpm: 60,
+        accuracy: 98,
+    });
+    bob.send(ClientMessage::Finish {
+        wpm: 75,
+        accuracy: 95,
+    });
+
+    for client in [&mut alice, &mut bob] {
+        let results =
+            client.receive_until(|message| matches!(message, ServerMessage::Results { .. }));
+        if let ServerMessage::Results { racers } = results {
+            let ranking: Vec<(&str, Option<u16>)> = racers
+                .iter()
+                .map(|racer| {
+                    (
+                        racer.name.as_str(),
+                        racer.result.as_ref().map(|result| result.wpm),
+                    )
+                })
+                .collect();
+            assert_eq!(ranking, [("Bob", Some(75)), ("Alice", Some(60))]);
+        }
+    }
+}
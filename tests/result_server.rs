@@ -0,0 +1,140 @@
+// Runs the result server binary and posts results to it the way the app does
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+#[path = "../src/result_posting.rs"]
+mod result_posting;
+
+use result_posting::post_results;
+
+const TOKEN: &str = "team secret";
+
+/// Kills the server when the test ends, whether it passed or not
+struct Server {
+    child: Child,
+    /// Kept open, as the server fails to print once nothing reads what it prints
+    _output: BufReader<ChildStdout>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts the server on a free port, wanting the token, and returns the endpoint it printed
+fn start_server(file: &Path) -> (Server, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_result_server"))
+        .args(["127.0.0.1:0", "--token", TOKEN, "--file"])
+        .arg(file)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the result server should start");
+
+    let mut output = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    output.read_line(&mut line).unwrap();
+    let endpoint = line
+        .trim()
+        .strip_prefix("Accepting results on ")
+        .and_then(|rest| rest.split(',').next())
+        .expect("the server should print its endpoint")
+        .to_string();
+
+    (
+        Server {
+            child,
+            _output: output,
+        },
+        endpoint,
+    )
+}
+
+/// The fields the server checks, as the app's queue has them
+#[derive(Serialize)]
+struct QueuedResult {
+    id: String,
+    name: String,
+    mode: String,
+    seconds: u16,
+    wpm: u16,
+}
+
+fn queued(id: &str, wpm: u16) -> QueuedResult {
+    QueuedResult {
+        id: id.to_string(),
+        name: "ada".to_string(),
+        mode: "Random Words".to_string(),
+        seconds: 60,
+        wpm,
+    }
+}
+
+fn stored_ids(file: &Path) -> Vec<String> {
+    std::fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            let result: serde_json::Value = serde_json::from_str(line).unwrap();
+            result["id"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn queued_results_are_stored_once_even_when_sent_again() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("results.jsonl");
+    let (_server, endpoint) = start_server(&file);
+
+    let outcome = post_results(&endpoint, TOKEN, &[queued("ada-1", 80)]);
+    assert_eq!((outcome.sent, outcome.refused, outcome.error), (1, 0, None));
+
+    // The answer to the first attempt got lost, so the queue still holds ada-1
+    let outcome = post_results(
+        &endpoint,
+        TOKEN,
+        &[queued("ada-1", 80), queued("ada-2", 85)],
+    );
+    assert_eq!((outcome.sent, outcome.refused, outcome.error), (2, 0, None));
+
+    assert_eq!(stored_ids(&file), ["ada-1", "ada-2"]);
+}
+
+#[test]
+fn results_without_the_token_stay_queued() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("results.jsonl");
+    let (_server, endpoint) = start_server(&file);
+
+    let outcome = post_results(&endpoint, "", &[queued("ada-1", 80), queued("ada-2", 85)]);
+    assert_eq!((outcome.sent, outcome.refused), (0, 0));
+    assert_eq!(
+        outcome.error.as_deref(),
+        Some("the server answered with status 401")
+    );
+
+    let outcome = post_results(&endpoint, "wrong secret", &[queued("ada-1", 80)]);
+    assert_eq!(outcome.sent, 0);
+    assert!(stored_ids(&file).is_empty());
+}
+
+#[test]
+fn a_refused_result_is_dropped_and_the_rest_are_sent() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("results.jsonl");
+    let (_server, endpoint) = start_server(&file);
+
+    // Missing the fields the server needs, so it answers 400
+    let queue = [
+        serde_json::json!({ "id": "broken", "name": "ada" }),
+        serde_json::to_value(queued("ada-1", 80)).unwrap(),
+    ];
+    let outcome = post_results(&endpoint, TOKEN, &queue);
+    assert_eq!((outcome.sent, outcome.refused, outcome.error), (2, 1, None));
+
+    assert_eq!(stored_ids(&file), ["ada-1"]);
+}